@@ -0,0 +1,34 @@
+// One group per day, measuring the generator and each part separately.
+// Inputs in benches/inputs are synthetic (made to look like real ones, but not from AoC),
+// so they can be committed and everyone benchmarks the same thing.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use aoc22::solver::{DAYS, PARTS};
+
+fn input(day: u32) -> String {
+    let path = format!("{}/benches/inputs/day{day}.txt", env!("CARGO_MANIFEST_DIR"));
+    let input = std::fs::read_to_string(&path).unwrap_or_else(|_| panic!("Missing {path}"));
+    input.trim_end_matches('\n').to_string()
+}
+
+fn bench_days(c: &mut Criterion) {
+    for day in DAYS {
+        let input = input(day);
+        let mut group = c.benchmark_group(format!("day{day}"));
+
+        group.bench_function("generator", |b| {
+            b.iter(|| aoc22::generate(day, black_box(&input)).unwrap())
+        });
+        let solver = aoc22::generate(day, &input).unwrap();
+        for part in PARTS {
+            group.bench_function(format!("part{part}"), |b| {
+                b.iter(|| solver.solve(black_box(part)).unwrap())
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_days);
+criterion_main!(benches);
@@ -0,0 +1,147 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context};
+use aoc22::solver::{DAYS, PARTS};
+
+const USAGE: &str =
+    "Usage: aoc22 (--day <N> | --all) [--part <1|2>] [--input <file|->] [--input-dir <dir>]
+
+  --day <N>          Solve a single day
+  --all              Solve every available day, skipping the ones without input
+  --part <1|2>       Only solve one part (both by default)
+  --input <file|->   Read the input of --day from a file, or from stdin with -
+  --input-dir <dir>  Where to find dayN.txt inputs (default: input/2022)";
+
+struct Args {
+    days: Vec<u32>,
+    parts: Vec<u32>,
+    input: Option<String>,
+    input_dir: PathBuf,
+}
+
+fn parse_args() -> anyhow::Result<Args> {
+    let mut day = None;
+    let mut all = false;
+    let mut part = None;
+    let mut input = None;
+    let mut input_dir = PathBuf::from("input/2022");
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| anyhow!("{arg} needs a value"));
+        match arg.as_str() {
+            "--day" => day = Some(value()?.parse::<u32>().context("Invalid --day")?),
+            "--part" => part = Some(value()?.parse::<u32>().context("Invalid --part")?),
+            "--input" => input = Some(value()?),
+            "--input-dir" => input_dir = PathBuf::from(value()?),
+            "--all" => all = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                std::process::exit(0);
+            }
+            _ => bail!("Unknown argument {arg}\n\n{USAGE}"),
+        }
+    }
+
+    let days = match (day, all) {
+        (Some(day), false) if DAYS.contains(&day) => vec![day],
+        (Some(day), false) => bail!("No solution for day {day}"),
+        (None, true) => DAYS.to_vec(),
+        _ => bail!("Exactly one of --day or --all is needed\n\n{USAGE}"),
+    };
+    if all && input.is_some() {
+        bail!("--input can only be used with --day");
+    }
+    let parts = match part {
+        Some(part) if PARTS.contains(&part) => vec![part],
+        Some(part) => bail!("Part should be 1 or 2, got {part}"),
+        None => PARTS.to_vec(),
+    };
+
+    Ok(Args {
+        days,
+        parts,
+        input,
+        input_dir,
+    })
+}
+
+fn read_input(args: &Args, day: u32) -> anyhow::Result<Option<String>> {
+    match args.input.as_deref() {
+        Some("-") => {
+            let mut input = String::new();
+            std::io::stdin().read_to_string(&mut input)?;
+            Ok(Some(input))
+        }
+        Some(file) => read_file(Path::new(file)).map(Some),
+        None => {
+            let path = args.input_dir.join(format!("day{day}.txt"));
+            // With --all, days we don't have an input for are skipped
+            if args.days.len() > 1 && !path.exists() {
+                return Ok(None);
+            }
+            read_file(&path).map(Some)
+        }
+    }
+}
+
+fn read_file(path: &Path) -> anyhow::Result<String> {
+    std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = parse_args()?;
+    // Days and parts that fail don't stop the others
+    let mut failures = 0;
+
+    for &day in &args.days {
+        let Some(input) = read_input(&args, day)? else {
+            println!("Day {day}: no input, skipped\n");
+            continue;
+        };
+        // Inputs downloaded by hand usually end with a newline that the generators don't expect
+        let input = input.trim_end_matches('\n');
+
+        println!("Day {day}");
+        let (solver, parse_time) = timed(|| aoc22::generate(day, input));
+        let solver = match solver {
+            Ok(solver) => solver,
+            Err(err) => {
+                println!("  generator: error: {err}\n");
+                failures += 1;
+                continue;
+            }
+        };
+        println!("  generator: {parse_time:?}");
+        for &part in &args.parts {
+            let (answer, solve_time) = timed(|| solver.solve(part));
+            let answer = match answer {
+                Ok(answer) => answer.to_string(),
+                Err(err) => {
+                    println!("  part {part}: error: {err}");
+                    failures += 1;
+                    continue;
+                }
+            };
+            if answer.contains('\n') {
+                println!("  part {part} ({solve_time:?}):\n{answer}");
+            } else {
+                println!("  part {part}: {answer} ({solve_time:?})");
+            }
+        }
+        println!();
+    }
+
+    if failures > 0 {
+        bail!("{failures} of the generators and parts failed");
+    }
+    Ok(())
+}
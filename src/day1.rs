@@ -1,18 +1,21 @@
+use crate::error::{Line, ParseError};
+use crate::solver::{DaySolver, Solver};
+
 #[aoc_generator(day1)]
-fn parse(input: &str) -> Vec<i32> {
+fn parse(input: &str) -> Result<Vec<i32>, ParseError> {
     let mut calories = Vec::new();
     let mut buffer = 0;
-    for i in input.lines() {
-        if i == "" {
+    for line in Line::all(1, input) {
+        if line.text.is_empty() {
             calories.push(buffer);
             buffer = 0;
             continue;
         }
-        buffer += i.parse::<i32>().unwrap();
+        buffer += line.parse::<i32>(line.text, "a calorie count")?;
     }
     calories.push(buffer);
     calories.sort_by(|a, b| b.cmp(a));
-    calories
+    Ok(calories)
 }
 
 #[aoc(day1, part1)]
@@ -25,10 +28,20 @@ fn part2(calories: &[i32]) -> i32 {
     calories.iter().take(3).sum()
 }
 
+pub(crate) fn generator(input: &str) -> Result<Box<dyn Solver>, ParseError> {
+    let solver = DaySolver::new(
+        1,
+        parse(input)?,
+        |i| Ok(part1(i).into()),
+        |i| Ok(part2(i).into()),
+    );
+    Ok(Box::new(solver))
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    const INPUT: &'static str = "1000
+    const INPUT: &str = "1000
 2000
 3000
 
@@ -45,9 +58,15 @@ pub mod tests {
 
     #[test]
     fn solve_day_1() {
-        let input = parse(INPUT);
+        let input = parse(INPUT).unwrap();
         dbg!(&input);
         assert_eq!(part1(&input), 24000);
         assert_eq!(part2(&input), 45000);
     }
+
+    #[test]
+    fn day_1_parse_error() {
+        let err = parse("1000\n\n20x0").unwrap_err();
+        assert_eq!(err, ParseError::new(1, 3, 1, "a calorie count"));
+    }
 }
@@ -1,78 +1,447 @@
-use itertools::Itertools;
+use std::fmt;
 
-enum Instruction {
-    Noop,
-    Addx(i32),
+use crate::error::{Line, ParseError};
+use crate::solver::{DaySolver, Solver};
+
+/// What an instruction does : how many cycles it takes, and how it changes the registers at
+/// the end of the last one
+#[derive(Debug, Clone, Copy)]
+pub struct Opcode {
+    pub mnemonic: &'static str,
+    /// Number of values after the mnemonic
+    pub operands: usize,
+    pub cycles: usize,
+    /// Number of registers the effect reads or writes, X included
+    pub registers: usize,
+    /// Called with the registers (X first) and the values of the instruction
+    pub effect: fn(&mut [i32], &[i32]),
 }
 
-#[aoc_generator(day10)]
-fn parse(input: &str) -> Vec<Instruction> {
-    input
-        .lines()
-        .map(|l| {
-            if l.starts_with("addx") {
-                let mut input = l.split(" ");
-                let _addx = input.next().unwrap();
-                let value = input.next().unwrap().parse().unwrap();
-                return Instruction::Addx(value);
-            }
-            return Instruction::Noop;
+const NOOP: Opcode = Opcode {
+    mnemonic: "noop",
+    operands: 0,
+    cycles: 1,
+    registers: 1,
+    effect: |_, _| (),
+};
+
+const ADDX: Opcode = Opcode {
+    mnemonic: "addx",
+    operands: 1,
+    cycles: 2,
+    registers: 1,
+    effect: |registers, values| registers[0] += values[0],
+};
+
+/// The opcodes a program can use
+#[derive(Debug, Clone)]
+pub struct InstructionSet {
+    opcodes: Vec<Opcode>,
+}
+
+impl Default for InstructionSet {
+    fn default() -> Self {
+        InstructionSet::standard()
+    }
+}
+
+impl InstructionSet {
+    /// noop and addx, like in the puzzle
+    pub fn standard() -> InstructionSet {
+        InstructionSet {
+            opcodes: vec![NOOP, ADDX],
+        }
+    }
+
+    /// Adds an opcode, or replaces the one with the same mnemonic
+    pub fn with(mut self, opcode: Opcode) -> InstructionSet {
+        self.opcodes.retain(|o| o.mnemonic != opcode.mnemonic);
+        self.opcodes.push(opcode);
+        self
+    }
+
+    fn parse_line(&self, line: Line) -> Result<Instruction, ParseError> {
+        let mut fields = line.text.split(' ');
+        let mnemonic = fields.next().unwrap_or_default();
+        let opcode = self
+            .opcodes
+            .iter()
+            .find(|o| o.mnemonic == mnemonic)
+            .ok_or_else(|| {
+                let mnemonics: Vec<_> = self.opcodes.iter().map(|o| o.mnemonic).collect();
+                line.error(mnemonic, format!("one of {}", mnemonics.join(", ")))
+            })?;
+        let operands = (0..opcode.operands)
+            .map(|_| {
+                let value = line.field(fields.next(), "a value")?;
+                line.parse(value, "a value")
+            })
+            .collect::<Result<_, _>>()?;
+        if let Some(extra) = fields.next() {
+            return Err(line.error(extra, format!("the end of {}", opcode.mnemonic)));
+        }
+        Ok(Instruction {
+            opcode: *opcode,
+            operands,
         })
-        .collect()
+    }
+
+    pub fn parse(&self, input: &str) -> Result<Vec<Instruction>, ParseError> {
+        Line::all(10, input)
+            .map(|line| self.parse_line(line))
+            .collect()
+    }
 }
 
-// This does allocate a Vec when we probably don't need it, but it makes part1 and part2
-// straightforward to read, so we'll take it
-fn run_instructions_to_completion(instructions: &[Instruction]) -> Vec<i32> {
-    let mut x = 1;
-    let mut signals = Vec::new();
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    opcode: Opcode,
+    operands: Vec<i32>,
+}
+
+#[aoc_generator(day10)]
+fn parse(input: &str) -> Result<Vec<Instruction>, ParseError> {
+    InstructionSet::standard().parse(input)
+}
+
+// Called with the cycle number (starting at 1) and the value of X
+type Observer<'a> = Box<dyn FnMut(usize, i32) + 'a>;
+
+/// Executes instructions cycle by cycle, and tells observers about the X register
+pub struct Cpu<'a> {
+    /// X first, then whatever other registers the instructions need
+    registers: Vec<i32>,
+    cycle: usize,
+    // Observers for a single cycle have it alongside
+    during: Vec<(Option<usize>, Observer<'a>)>,
+    after: Vec<Observer<'a>>,
+}
+
+impl<'a> Default for Cpu<'a> {
+    fn default() -> Self {
+        Cpu::new()
+    }
+}
+
+impl<'a> Cpu<'a> {
+    pub fn new() -> Cpu<'a> {
+        Cpu::with_registers(1)
+    }
 
-    for i in instructions {
-        match i {
-            Instruction::Addx(value) => {
-                signals.push(x);
-                signals.push(x);
-                x += value;
+    /// A CPU with that many registers from the start, all of them at 1. Registers that
+    /// instructions need are added when they execute anyway
+    pub fn with_registers(count: usize) -> Cpu<'a> {
+        Cpu {
+            registers: vec![1; count.max(1)],
+            cycle: 0,
+            during: Vec::new(),
+            after: Vec::new(),
+        }
+    }
+
+    pub fn x(&self) -> i32 {
+        self.registers[0]
+    }
+
+    pub fn registers(&self) -> &[i32] {
+        &self.registers
+    }
+
+    /// Number of cycles that are over
+    pub fn cycle(&self) -> usize {
+        self.cycle
+    }
+
+    /// Observes X during that cycle only
+    pub fn during(&mut self, cycle: usize, mut f: impl FnMut(i32) + 'a) -> &mut Self {
+        self.during.push((Some(cycle), Box::new(move |_, x| f(x))));
+        self
+    }
+
+    /// Observes X during every cycle, before the instruction changes it
+    pub fn during_every_cycle(&mut self, f: impl FnMut(usize, i32) + 'a) -> &mut Self {
+        self.during.push((None, Box::new(f)));
+        self
+    }
+
+    /// Observes X at the end of every cycle, once the instruction changed it
+    pub fn after_every_cycle(&mut self, f: impl FnMut(usize, i32) + 'a) -> &mut Self {
+        self.after.push(Box::new(f));
+        self
+    }
+
+    pub fn execute(&mut self, instruction: &Instruction) {
+        let Instruction { opcode, operands } = instruction;
+        for remaining in (0..opcode.cycles).rev() {
+            self.cycle += 1;
+            for (cycle, observer) in self.during.iter_mut() {
+                if cycle.is_none_or(|c| c == self.cycle) {
+                    observer(self.cycle, self.registers[0]);
+                }
             }
-            Instruction::Noop => {
-                signals.push(x);
+            if remaining == 0 {
+                if self.registers.len() < opcode.registers {
+                    self.registers.resize(opcode.registers, 1);
+                }
+                (opcode.effect)(&mut self.registers, operands);
+            }
+            for observer in self.after.iter_mut() {
+                observer(self.cycle, self.registers[0]);
             }
         }
     }
 
+    /// Executes the instructions as they come, so they don't have to be in memory
+    pub fn run<'i>(&mut self, instructions: impl IntoIterator<Item = &'i Instruction>) {
+        for instruction in instructions {
+            self.execute(instruction);
+        }
+    }
+}
+
+/// X during every cycle, for when we need all of them at once
+pub fn run_instructions_to_completion(instructions: &[Instruction]) -> Vec<i32> {
+    let mut signals = Vec::new();
+    Cpu::new()
+        .during_every_cycle(|_, x| signals.push(x))
+        .run(instructions);
     signals
 }
 
 #[aoc(day10, part1)]
 fn part1(input: &[Instruction]) -> i32 {
-    let signals = run_instructions_to_completion(input);
-    signals[19] * 20
-        + signals[59] * 60
-        + signals[99] * 100
-        + signals[139] * 140
-        + signals[179] * 180
-        + signals[219] * 220
+    let mut strength = 0;
+    Cpu::new()
+        .during_every_cycle(|cycle, x| {
+            // Cycles 20, 60, 100, 140, 180 and 220
+            if cycle <= 220 && cycle % 40 == 20 {
+                strength += cycle as i32 * x;
+            }
+        })
+        .run(input);
+    strength
+}
+
+const CRT_WIDTH: usize = 40;
+
+// The sprite is 3 pixels wide, centered on X
+fn is_lit(column: usize, x: i32) -> bool {
+    (x - 1..=x + 1).contains(&(column as i32))
 }
 
 fn part2(input: &[Instruction]) -> String {
-    let signals = run_instructions_to_completion(input);
-    let lines = signals.len() / 40;
-    (0..lines)
-        .map(|line| {
-            (0..40)
-                .map(|crt| {
-                    let cycle = line * 40 + crt;
-                    let x = signals[cycle];
-                    let cycle = cycle as i32 % 40;
-                    if (cycle - 1..=cycle + 1).contains(&x) {
-                        '#'
-                    } else {
-                        '.'
-                    }
+    let mut lines = Vec::new();
+    let mut line = String::with_capacity(CRT_WIDTH);
+    Cpu::new()
+        .during_every_cycle(|cycle, x| {
+            let crt = (cycle - 1) % CRT_WIDTH;
+            line.push(if is_lit(crt, x) { '#' } else { '.' });
+            if crt == CRT_WIDTH - 1 {
+                lines.push(std::mem::take(&mut line));
+            }
+        })
+        .run(input);
+    lines.join("\n")
+}
+
+/// What the CRT drew, to be saved as an image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrtImage {
+    /// Whether each pixel is lit, row by row
+    pixels: Vec<bool>,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl CrtImage {
+    /// Draws the X values of each cycle (see run_instructions_to_completion) on a CRT that
+    /// wide. Like in part 2, a row that isn't complete isn't drawn. None if the CRT has no width.
+    pub fn from_signals(signals: &[i32], width: usize) -> Option<CrtImage> {
+        let height = signals.len().checked_div(width)?;
+        let pixels = signals[..width * height]
+            .iter()
+            .enumerate()
+            .map(|(cycle, x)| is_lit(cycle % width, *x))
+            .collect();
+        Some(CrtImage {
+            pixels,
+            width,
+            height,
+        })
+    }
+
+    // The width and height once each pixel is a square of that size, None if it is 0 or too big
+    fn scaled_size(&self, scale: usize) -> Option<(usize, usize)> {
+        if scale == 0 {
+            return None;
+        }
+        Some((
+            self.width.checked_mul(scale)?,
+            self.height.checked_mul(scale)?,
+        ))
+    }
+
+    // Every pixel of the image once each of them is a square of that size, row by row
+    fn scaled(&self, scale: usize) -> impl Iterator<Item = bool> + '_ {
+        self.pixels
+            .chunks(self.width)
+            .flat_map(move |row| std::iter::repeat_n(row, scale))
+            .flat_map(move |row| {
+                row.iter()
+                    .flat_map(move |pixel| std::iter::repeat_n(*pixel, scale))
+            })
+    }
+
+    /// Plain PBM, with lit pixels in black. None if the scale is 0.
+    pub fn to_pbm(&self, scale: usize) -> Option<String> {
+        let (width, height) = self.scaled_size(scale)?;
+        let mut pbm = format!("P1\n{width} {height}\n");
+        let pixels: Vec<bool> = self.scaled(scale).collect();
+        for row in pixels.chunks(width) {
+            let row: Vec<&str> = row.iter().map(|lit| if *lit { "1" } else { "0" }).collect();
+            pbm.push_str(&row.join(" "));
+            pbm.push('\n');
+        }
+        Some(pbm)
+    }
+
+    /// Greyscale PNG, with lit pixels in black. It isn't compressed, but the images are small.
+    /// None if the scale is 0.
+    pub fn to_png(&self, scale: usize) -> Option<Vec<u8>> {
+        let (width, height) = self.scaled_size(scale)?;
+        let pixels: Vec<bool> = self.scaled(scale).collect();
+        // Each row starts with its filter type, 0 being none
+        let mut raw = Vec::with_capacity(pixels.len() + height);
+        for row in pixels.chunks(width) {
+            raw.push(0);
+            raw.extend(row.iter().map(|lit| if *lit { 0 } else { 255 }));
+        }
+
+        let mut header = Vec::with_capacity(13);
+        header.extend((width as u32).to_be_bytes());
+        header.extend((height as u32).to_be_bytes());
+        // 8 bits greyscale, default compression and filter methods, not interlaced
+        header.extend([8, 0, 0, 0, 0]);
+
+        let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+        png_chunk(&mut png, b"IHDR", &header);
+        png_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+        png_chunk(&mut png, b"IEND", &[]);
+        Some(png)
+    }
+}
+
+fn png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    png.extend(kind);
+    png.extend(data);
+    let crc = !kind.iter().chain(data).fold(!0u32, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u32, |crc, _| match crc & 1 {
+            1 => (crc >> 1) ^ 0xedb88320,
+            _ => crc >> 1,
+        })
+    });
+    png.extend(crc.to_be_bytes());
+}
+
+// Zlib stream made of uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        zlib.push(last as u8);
+        zlib.extend((block.len() as u16).to_le_bytes());
+        zlib.extend((!(block.len() as u16)).to_le_bytes());
+        zlib.extend(block);
+    }
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), byte| {
+        let a = (a + *byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    zlib.extend(((b << 16) | a).to_be_bytes());
+    zlib
+}
+
+/// Letters as the CRT draws them : 4 pixels wide (plus a blank column between letters) and
+/// 6 pixels high. Only the letters that have been seen in puzzle answers are known.
+const FONT: [(char, [&str; 6]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+const LETTER_WIDTH: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OcrError {
+    /// Letters are 6 pixels high, so is the image
+    Height(usize),
+    /// The glyph at that position (starting at 1) isn't in the font
+    UnknownGlyph { position: usize, glyph: String },
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OcrError::Height(height) => write!(f, "expected 6 lines of pixels, got {height}"),
+            OcrError::UnknownGlyph { position, glyph } => {
+                write!(f, "unrecognised letter {position}:\n{glyph}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for OcrError {}
+
+/// Reads the letters of an image made of # and . (like part2 draws them)
+pub fn decode_crt(image: &str) -> Result<String, OcrError> {
+    let lines: Vec<&[u8]> = image.lines().map(str::as_bytes).collect();
+    if lines.len() != 6 {
+        return Err(OcrError::Height(lines.len()));
+    }
+    let width = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+    (0..width.div_ceil(LETTER_WIDTH))
+        .map(|position| {
+            let start = position * LETTER_WIDTH;
+            let glyph: Vec<String> = lines
+                .iter()
+                .map(|line| {
+                    // Lines can be shorter than the others, or miss the last blank column
+                    (start..start + 4)
+                        .map(|x| match line.get(x) {
+                            Some(b'#') => '#',
+                            _ => '.',
+                        })
+                        .collect()
+                })
+                .collect();
+            FONT.iter()
+                .find(|(_, pixels)| pixels.iter().eq(glyph.iter()))
+                .map(|(letter, _)| *letter)
+                .ok_or_else(|| OcrError::UnknownGlyph {
+                    position: position + 1,
+                    glyph: glyph.join("\n"),
                 })
-                .collect::<String>()
         })
-        .join("\n")
+        .collect()
 }
 
 #[aoc(day10, part2)]
@@ -83,11 +452,32 @@ fn part2_formatted(input: &[Instruction]) -> String {
     format!("\n{result}")
 }
 
+#[aoc(day10, part2, letters)]
+fn part2_letters(input: &[Instruction]) -> Result<String, OcrError> {
+    decode_crt(&part2(input))
+}
+
+// The letters are what AoC expects, but we keep the image when it doesn't hold any
+fn part2_answer(input: &[Instruction]) -> String {
+    let image = part2(input);
+    decode_crt(&image).unwrap_or(image)
+}
+
+pub(crate) fn generator(input: &str) -> Result<Box<dyn Solver>, ParseError> {
+    let solver = DaySolver::new(
+        10,
+        parse(input)?,
+        |i| Ok(part1(i).into()),
+        |i| Ok(part2_answer(i).into()),
+    );
+    Ok(Box::new(solver))
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
 
-    const INPUT: &'static str = "addx 15
+    const INPUT: &str = "addx 15
 addx -11
 addx 6
 addx -3
@@ -234,7 +624,7 @@ noop
 noop
 noop";
 
-    const IMAGE: &'static str = "##..##..##..##..##..##..##..##..##..##..
+    const IMAGE: &str = "##..##..##..##..##..##..##..##..##..##..
 ###...###...###...###...###...###...###.
 ####....####....####....####....####....
 #####.....#####.....#####.....#####.....
@@ -243,8 +633,149 @@ noop";
 
     #[test]
     fn solve_day_10() {
-        let input = parse(INPUT);
+        let input = parse(INPUT).unwrap();
         assert_eq!(part1(&input), 13140);
         assert_eq!(part2(&input), IMAGE);
     }
+
+    #[test]
+    fn day_10_ocr() {
+        let image = "###..####.#..#.####..##....##..##..###..
+#..#....#.#..#.#....#..#....#.#..#.#..#.
+#..#...#..####.###..#.......#.#....###..
+###...#...#..#.#....#.##....#.#....#..#.
+#.#..#....#..#.#....#..#.#..#.#..#.#..#.
+#..#.####.#..#.#.....###..##...##..###..";
+        assert_eq!(decode_crt(image), Ok("RZHFGJCB".to_string()));
+        assert_eq!(
+            decode_crt(IMAGE),
+            Err(OcrError::UnknownGlyph {
+                position: 1,
+                glyph: "##..\n###.\n####\n####\n####\n####".to_string()
+            })
+        );
+        assert_eq!(decode_crt("#..#"), Err(OcrError::Height(1)));
+    }
+
+    #[test]
+    fn day_10_parse_error() {
+        let err = parse("addx 3\nadd 2").unwrap_err();
+        assert_eq!(err, ParseError::new(10, 2, 1, "one of noop, addx"));
+        let err = parse("noop\naddx").unwrap_err();
+        assert_eq!(err, ParseError::new(10, 2, 5, "a value"));
+        let err = parse("addx 1 2").unwrap_err();
+        assert_eq!(err, ParseError::new(10, 1, 8, "the end of addx"));
+        let err = parse("noop 2").unwrap_err();
+        assert_eq!(err, ParseError::new(10, 1, 6, "the end of noop"));
+    }
+
+    #[test]
+    fn day_10_instruction_set() {
+        let set = InstructionSet::standard()
+            .with(Opcode {
+                mnemonic: "addy",
+                operands: 1,
+                cycles: 3,
+                registers: 2,
+                effect: |registers, values| registers[1] += values[0],
+            })
+            .with(Opcode {
+                mnemonic: "mulxy",
+                operands: 0,
+                cycles: 1,
+                registers: 2,
+                effect: |registers, _| registers[0] *= registers[1],
+            });
+        let program = set.parse("addy 4\naddx 2\nmulxy\nnoop").unwrap();
+        let mut during = Vec::new();
+        let mut cpu = Cpu::with_registers(2);
+        cpu.during_every_cycle(|_, x| during.push(x)).run(&program);
+        assert_eq!(cpu.registers(), [15, 5]);
+        drop(cpu);
+        assert_eq!(during, vec![1, 1, 1, 1, 1, 3, 15]);
+
+        // Y gets added once addy needs it
+        let mut cpu = Cpu::new();
+        cpu.run(&program[..1]);
+        assert_eq!(cpu.registers(), [1, 5]);
+        cpu.run(&program[1..]);
+        assert_eq!(cpu.registers(), [15, 5]);
+    }
+
+    #[test]
+    fn day_10_export() {
+        let input = parse(INPUT).unwrap();
+        let signals = run_instructions_to_completion(&input);
+        let image = CrtImage::from_signals(&signals, 40).unwrap();
+        assert_eq!((image.width, image.height), (40, 6));
+        let pbm = image.to_pbm(1).unwrap();
+        let rows: Vec<&str> = pbm.lines().collect();
+        assert_eq!(rows[..2], ["P1", "40 6"]);
+        assert_eq!(
+            rows[2..].join("\n"),
+            IMAGE
+                .replace('#', "1 ")
+                .replace('.', "0 ")
+                .replace(" \n", "\n")
+                .trim_end()
+        );
+
+        let pbm = image.to_pbm(2).unwrap();
+        let rows: Vec<&str> = pbm.lines().collect();
+        assert_eq!(rows.len(), 2 + 12);
+        assert_eq!(rows[1], "80 12");
+        assert!(rows[2].starts_with("1 1 1 1 0 0 0 0 1 1 1 1"));
+        assert_eq!(rows[2], rows[3]);
+
+        // The same 240 cycles on a narrower CRT
+        let image = CrtImage::from_signals(&signals, 20).unwrap();
+        assert_eq!((image.width, image.height), (20, 12));
+
+        let png = CrtImage::from_signals(&signals, 40)
+            .and_then(|image| image.to_png(3))
+            .unwrap();
+        assert_eq!(
+            png[..8],
+            [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']
+        );
+        assert_eq!(png[16..24], [0, 0, 0, 120, 0, 0, 0, 18]);
+        // The CRC of an empty IEND chunk is always the same
+        assert_eq!(
+            png[png.len() - 12..],
+            [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]
+        );
+    }
+
+    #[test]
+    fn day_10_export_empty_size() {
+        let input = parse(INPUT).unwrap();
+        let signals = run_instructions_to_completion(&input);
+        assert_eq!(CrtImage::from_signals(&signals, 0), None);
+        let image = CrtImage::from_signals(&signals, 40).unwrap();
+        assert_eq!(image.to_pbm(0), None);
+        assert_eq!(image.to_png(0), None);
+        assert_eq!(image.to_png(usize::MAX), None);
+    }
+
+    #[test]
+    fn day_10_cpu() {
+        let input = parse("noop\naddx 3\naddx -5").unwrap();
+        let mut during = Vec::new();
+        let mut after = Vec::new();
+        let mut third = None;
+        let mut cpu = Cpu::new();
+        cpu.during_every_cycle(|cycle, x| during.push((cycle, x)))
+            .after_every_cycle(|_, x| after.push(x))
+            .during(3, |x| third = Some(x))
+            .run(&input);
+        assert_eq!((cpu.cycle(), cpu.x()), (5, -1));
+        drop(cpu);
+        assert_eq!(during, vec![(1, 1), (2, 1), (3, 1), (4, 4), (5, 4)]);
+        assert_eq!(after, vec![1, 1, 4, 4, -1]);
+        assert_eq!(third, Some(1));
+
+        let input = parse(INPUT).unwrap();
+        let signals = run_instructions_to_completion(&input);
+        assert_eq!((signals.len(), signals[19], signals[219]), (240, 21, 18));
+    }
 }
@@ -1,116 +1,444 @@
-#[derive(Debug)]
-enum Value {
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::error::{Line, ParseError};
+use crate::solver::{DaySolver, Solver};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinOp {
+    fn parse(token: &str) -> Option<BinOp> {
+        match token {
+            "+" => Some(BinOp::Add),
+            "-" => Some(BinOp::Sub),
+            "*" => Some(BinOp::Mul),
+            "/" => Some(BinOp::Div),
+            "%" => Some(BinOp::Rem),
+            _ => None,
+        }
+    }
+
+    fn is_additive(&self) -> bool {
+        matches!(self, BinOp::Add | BinOp::Sub)
+    }
+
+    fn apply(&self, a: i64, b: i64) -> Result<i64, EvalError> {
+        let result = match self {
+            BinOp::Add => a.checked_add(b),
+            BinOp::Sub => a.checked_sub(b),
+            BinOp::Mul => a.checked_mul(b),
+            BinOp::Div | BinOp::Rem if b == 0 => return Err(EvalError::DivisionByZero),
+            BinOp::Div => a.checked_div(b),
+            BinOp::Rem => a.checked_rem(b),
+        };
+        result.ok_or(EvalError::Overflow)
+    }
+}
+
+// Why an operation couldn't give a new worry level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EvalError {
+    Overflow,
+    DivisionByZero,
+}
+
+impl EvalError {
+    // The error for a monkey inspecting an item with that worry level
+    fn inspecting(self, round: usize, monkey: usize, item: usize, worry_level: i64) -> WorryError {
+        match self {
+            EvalError::Overflow => WorryError::Overflow {
+                round,
+                monkey,
+                item,
+                worry_level,
+            },
+            EvalError::DivisionByZero => WorryError::DivisionByZero {
+                round,
+                monkey,
+                item,
+            },
+        }
+    }
+}
+
+/// How a monkey computes the new worry level of an item from the old one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
     Old,
-    Fixed(i64),
+    Literal(i64),
+    Binary(Box<Expr>, BinOp, Box<Expr>),
 }
 
-impl Value {
-    fn parse(input: &str) -> Value {
-        match input {
-            "old" => Value::Old,
-            x => Value::Fixed(x.parse().unwrap()),
+impl Expr {
+    /// Parses the right side of "new = ...", which should be a slice of the line
+    fn parse(line: Line, text: &str) -> Result<Expr, ParseError> {
+        let mut parser = ExprParser { line, rest: text };
+        let expr = parser.sum()?;
+        match parser.peek() {
+            "" => Ok(expr),
+            token => Err(line.error(token, "+, -, *, /, % or the end of the operation")),
+        }
+    }
+
+    /// Fails if the computation overflows, or divides by 0
+    fn eval(&self, old: i64) -> Result<i64, EvalError> {
+        match self {
+            Expr::Old => Ok(old),
+            Expr::Literal(value) => Ok(*value),
+            Expr::Binary(a, op, b) => op.apply(a.eval(old)?, b.eval(old)?),
         }
     }
 
-    fn value(&self, old: i64) -> i64 {
+    /// Whether the result modulo any number only depends on old modulo that number, which
+    /// holds until we divide or take a remainder
+    fn is_modular(&self) -> bool {
         match self {
-            Value::Old => old,
-            Value::Fixed(x) => *x,
+            Expr::Old | Expr::Literal(_) => true,
+            Expr::Binary(a, op, b) => {
+                !matches!(op, BinOp::Div | BinOp::Rem) && a.is_modular() && b.is_modular()
+            }
         }
     }
 }
 
-#[derive(Debug)]
-enum Operation {
-    Add(Value),
-    Mul(Value),
+// Recursive descent, with * / % before + -, and left to right otherwise
+struct ExprParser<'a> {
+    line: Line<'a>,
+    rest: &'a str,
 }
 
-impl Operation {
-    fn parse(input: &str, value: Value) -> Operation {
-        match input {
-            "*" => Operation::Mul(value),
-            "+" => Operation::Add(value),
-            _ => unimplemented!(),
+impl<'a> ExprParser<'a> {
+    // Next token, without consuming it : a word, a number, or a single symbol.
+    // At the end, that's an empty slice at the end of the line.
+    fn peek(&mut self) -> &'a str {
+        self.rest = self.rest.trim_start();
+        let len = match self.rest.chars().next() {
+            None => 0,
+            Some(c) if c.is_ascii_alphanumeric() => self
+                .rest
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(self.rest.len()),
+            Some(c) => c.len_utf8(),
+        };
+        &self.rest[..len]
+    }
+
+    fn next(&mut self) -> &'a str {
+        let token = self.peek();
+        self.rest = &self.rest[token.len()..];
+        token
+    }
+
+    fn sum(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.product()?;
+        while let Some(op) = BinOp::parse(self.peek()).filter(BinOp::is_additive) {
+            self.next();
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.product()?));
+        }
+        Ok(expr)
+    }
+
+    fn product(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.factor()?;
+        while let Some(op) = BinOp::parse(self.peek()).filter(|op| !op.is_additive()) {
+            self.next();
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.factor()?));
+        }
+        Ok(expr)
+    }
+
+    fn factor(&mut self) -> Result<Expr, ParseError> {
+        match self.next() {
+            "old" => Ok(Expr::Old),
+            "(" => {
+                let expr = self.sum()?;
+                match self.next() {
+                    ")" => Ok(expr),
+                    token => Err(self.line.error(token, "\")\"")),
+                }
+            }
+            token => self
+                .line
+                .parse(token, "\"old\", a number or \"(\"")
+                .map(Expr::Literal),
         }
     }
 }
 
 #[derive(Debug)]
-struct MonkeyNote {
+pub struct MonkeyNote {
     starting_items: Vec<i64>,
-    operation: Operation,
+    operation: Expr,
     test_divisible_by: i64,
     if_true: usize,
     if_false: usize,
 }
 
-impl MonkeyNote {
-    fn parse(input: &str) -> MonkeyNote {
-        let mut lines = input.lines();
+// Returns the line at idx in the note, and what follows the expected prefix
+fn note_line<'a>(
+    note: &[Line<'a>],
+    idx: usize,
+    prefix: &str,
+) -> Result<(Line<'a>, &'a str), ParseError> {
+    match note.get(idx) {
+        Some(line) => Ok((*line, line.strip_prefix(prefix)?)),
+        None => {
+            let last = note[note.len() - 1];
+            Err(ParseError::new(
+                11,
+                last.number + 1,
+                1,
+                format!("{prefix:?}"),
+            ))
+        }
+    }
+}
 
-        let _idx = lines.next().unwrap();
+impl MonkeyNote {
+    fn parse(note: &[Line], monkey_count: usize) -> Result<MonkeyNote, ParseError> {
+        let _idx = note_line(note, 0, "Monkey ")?;
 
-        let start_line = lines.next().unwrap();
-        let starting_items = start_line["  Starting items: ".len()..]
+        let (start_line, items) = note_line(note, 1, "  Starting items: ")?;
+        let starting_items = items
             .split(", ")
-            .map(|x| x.parse().unwrap())
-            .collect();
-
-        let op_line = lines.next().unwrap();
-        let mut operation_split = op_line["  Operation: new = old ".len()..].split(" ");
-        let op = operation_split.next().unwrap();
-        let value = operation_split.next().map(Value::parse).unwrap();
-        let operation = Operation::parse(op, value);
+            .map(|x| start_line.parse(x, "a worry level"))
+            .collect::<Result<_, _>>()?;
 
-        let test_line = lines.next().unwrap();
-        let test_divisible_by = test_line["  Test: divisible by ".len()..].parse().unwrap();
+        let (op_line, op) = note_line(note, 2, "  Operation: new = ")?;
+        let operation = Expr::parse(op_line, op)?;
 
-        let if_true_line = lines.next().unwrap();
-        let if_true = if_true_line["    If true: throw to monkey ".len()..]
-            .parse()
-            .unwrap();
+        let (test_line, test) = note_line(note, 3, "  Test: divisible by ")?;
+        let test_divisible_by = match test_line.parse(test, "a divisor")? {
+            0 => return Err(test_line.error(test, "a divisor")),
+            divisor => divisor,
+        };
 
-        let if_false_line = lines.next().unwrap();
-        let if_false = if_false_line["    If false: throw to monkey ".len()..]
-            .parse()
-            .unwrap();
+        let target = |idx: usize, prefix: &str| -> Result<usize, ParseError> {
+            let (line, target) = note_line(note, idx, prefix)?;
+            let expected = format!("a monkey between 0 and {}", monkey_count - 1);
+            match line.parse::<usize>(target, &expected)? {
+                t if t < monkey_count => Ok(t),
+                _ => Err(line.error(target, expected)),
+            }
+        };
+        let if_true = target(4, "    If true: throw to monkey ")?;
+        let if_false = target(5, "    If false: throw to monkey ")?;
 
-        MonkeyNote {
+        Ok(MonkeyNote {
             starting_items,
             operation,
             test_divisible_by,
             if_true,
             if_false,
-        }
+        })
+    }
+}
+
+impl MonkeyNote {
+    /// Notes of every monkey, in order
+    pub fn parse_all(input: &str) -> Result<Vec<MonkeyNote>, ParseError> {
+        parse(input)
     }
 }
 
 #[aoc_generator(day11)]
-fn parse(input: &str) -> Vec<MonkeyNote> {
-    input.split("\n\n").map(MonkeyNote::parse).collect()
+fn parse(input: &str) -> Result<Vec<MonkeyNote>, ParseError> {
+    let lines: Vec<Line> = Line::all(11, input).collect();
+    let notes: Vec<&[Line]> = lines
+        .split(|l| l.text.is_empty())
+        .filter(|note| !note.is_empty())
+        .collect();
+    notes
+        .iter()
+        .map(|note| MonkeyNote::parse(note, notes.len()))
+        .collect()
 }
 
 #[derive(Debug)]
-struct Monkey {
+pub struct Monkey {
     worry_levels: Vec<i64>,
     total_items: usize,
 }
 
-fn do_round<F: Fn(i64) -> i64>(notes: &[MonkeyNote], monkeys: &mut Vec<Monkey>, reduce_worry: F) {
+impl Monkey {
+    fn new(note: &MonkeyNote) -> Monkey {
+        Monkey {
+            worry_levels: note.starting_items.clone(),
+            total_items: 0,
+        }
+    }
+
+    /// Worry levels of the items the monkey holds, in order
+    pub fn items(&self) -> &[i64] {
+        &self.worry_levels
+    }
+
+    /// How many items the monkey inspected so far
+    pub fn inspected(&self) -> usize {
+        self.total_items
+    }
+}
+
+/// A monkey looking at an item, and throwing it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Inspection {
+    pub round: usize,
+    pub monkey: usize,
+    /// Worry level before the inspection
+    pub old: i64,
+    /// Worry level once the monkey is done with the item, relief included
+    pub new: i64,
+    pub divisible: bool,
+    pub target: usize,
+}
+
+/// Gets told about everything that happens during the rounds.
+/// `()` doesn't do anything, so that there's no cost when we're not tracing.
+pub trait Tracer {
+    fn inspection(&mut self, _inspection: &Inspection) {}
+    fn end_of_round(&mut self, _round: usize, _monkeys: &[Monkey]) {}
+}
+
+impl Tracer for () {}
+
+/// Records every inspection, and what the monkeys hold after each round
+#[derive(Debug, Default)]
+pub struct Trace {
+    pub inspections: Vec<Inspection>,
+    // Items of each monkey, after each round
+    holding: Vec<Vec<Vec<i64>>>,
+}
+
+impl Tracer for Trace {
+    fn inspection(&mut self, inspection: &Inspection) {
+        self.inspections.push(*inspection);
+    }
+
+    fn end_of_round(&mut self, _round: usize, monkeys: &[Monkey]) {
+        let holding = monkeys.iter().map(|m| m.worry_levels.clone()).collect();
+        self.holding.push(holding);
+    }
+}
+
+impl Trace {
+    /// What the monkeys hold after a round (starting at 1), like in the puzzle.
+    /// None if the round isn't part of the trace.
+    pub fn round_summary(&self, round: usize) -> Option<String> {
+        let holding = self.holding.get(round.checked_sub(1)?)?;
+        let mut summary =
+            format!("After round {round}, the monkeys are holding items with these worry levels:");
+        for (monkey, items) in holding.iter().enumerate() {
+            let items: Vec<String> = items.iter().map(|i| i.to_string()).collect();
+            summary.push_str(&format!("\nMonkey {monkey}: {}", items.join(", ")));
+        }
+        Some(summary)
+    }
+
+    /// Summaries of every round, separated by blank lines
+    pub fn summaries(&self) -> String {
+        (1..=self.holding.len())
+            .filter_map(|round| self.round_summary(round))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorryError {
+    /// A monkey couldn't compute the new worry level of an item
+    Overflow {
+        /// Starting at 1
+        round: usize,
+        monkey: usize,
+        /// Position of the item in what the monkey is holding (in its starting items, when
+        /// items are followed one by one)
+        item: usize,
+        worry_level: i64,
+    },
+    /// The operation of a monkey divided by 0, or took a remainder by 0, on an item
+    DivisionByZero {
+        round: usize,
+        monkey: usize,
+        item: usize,
+    },
+    /// The operation of a monkey divides or takes a remainder, so worry levels can't be kept
+    /// modulo the divisors
+    NotModular { monkey: usize },
+    /// The divisors up to that monkey's have no common multiple that fits in the worry levels
+    ModulusOverflow { monkey: usize },
+}
+
+impl fmt::Display for WorryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorryError::Overflow {
+                round,
+                monkey,
+                item,
+                worry_level,
+            } => write!(
+                f,
+                "round {}: monkey {} overflowed inspecting item {} (worry level {})",
+                round, monkey, item, worry_level
+            ),
+            WorryError::DivisionByZero {
+                round,
+                monkey,
+                item,
+            } => write!(
+                f,
+                "round {round}: monkey {monkey} divided by 0 inspecting item {item}"
+            ),
+            WorryError::NotModular { monkey } => write!(
+                f,
+                "monkey {monkey} divides worry levels, they can't be kept modulo the divisors"
+            ),
+            WorryError::ModulusOverflow { monkey } => write!(
+                f,
+                "the divisors up to monkey {monkey} are too large to keep worry levels modulo them"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WorryError {}
+
+fn do_round<F: Fn(i64) -> i64, T: Tracer>(
+    notes: &[MonkeyNote],
+    monkeys: &mut [Monkey],
+    round: usize,
+    reduce_worry: F,
+    tracer: &mut T,
+) -> Result<(), WorryError> {
     for i in 0..monkeys.len() {
         let mut send_list = Vec::new();
         monkeys[i].total_items += monkeys[i].worry_levels.len();
-        for item in monkeys[i].worry_levels.drain(..) {
-            let worry_level = match &notes[i].operation {
-                Operation::Add(v) => item + v.value(item),
-                Operation::Mul(v) => item * v.value(item),
-            };
+        for (item, old) in monkeys[i].worry_levels.drain(..).enumerate() {
+            let worry_level = notes[i]
+                .operation
+                .eval(old)
+                .map_err(|err| err.inspecting(round, i, item, old))?;
             let worry_level = reduce_worry(worry_level);
-            let target_monkey = match worry_level % notes[i].test_divisible_by == 0 {
+            let divisible = worry_level % notes[i].test_divisible_by == 0;
+            let target_monkey = match divisible {
                 true => notes[i].if_true,
                 false => notes[i].if_false,
             };
+            tracer.inspection(&Inspection {
+                round,
+                monkey: i,
+                old,
+                new: worry_level,
+                divisible,
+                target: target_monkey,
+            });
             send_list.push((target_monkey, worry_level));
         }
 
@@ -118,50 +446,210 @@ fn do_round<F: Fn(i64) -> i64>(notes: &[MonkeyNote], monkeys: &mut Vec<Monkey>,
             monkeys[i].worry_levels.push(wl);
         }
     }
+    tracer.end_of_round(round, monkeys);
+    Ok(())
 }
 
-#[aoc(day11, part1)]
-fn part1(notes: &[MonkeyNote]) -> usize {
-    let mut monkeys: Vec<Monkey> = notes
+/// What happens to the worry level of an item once a monkey inspected it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorryRule {
+    /// Divided by 3, since we're relieved the item isn't damaged (part 1)
+    Relief,
+    /// Kept modulo the divisors of the tests, which doesn't change any of them (part 2).
+    /// Only works when the operations add, subtract and multiply.
+    Modulo,
+    /// Left as is, until it overflows
+    Unchanged,
+}
+
+fn run_rounds<T: Tracer>(
+    notes: &[MonkeyNote],
+    rounds: usize,
+    rule: WorryRule,
+    tracer: &mut T,
+) -> Result<Vec<Monkey>, WorryError> {
+    // The other rules don't need the modulus, and it may not fit anyway
+    let modulus = match rule {
+        WorryRule::Modulo => {
+            check_modular(notes)?;
+            divisors_lcm(notes)?
+        }
+        WorryRule::Relief | WorryRule::Unchanged => 1,
+    };
+    let reduce_worry = |wl: i64| match rule {
+        WorryRule::Relief => wl / 3,
+        WorryRule::Modulo => wl % modulus,
+        WorryRule::Unchanged => wl,
+    };
+    let mut monkeys: Vec<Monkey> = notes.iter().map(Monkey::new).collect();
+    for round in 1..=rounds {
+        do_round(notes, &mut monkeys, round, reduce_worry, tracer)?;
+    }
+    Ok(monkeys)
+}
+
+/// How many items each monkey inspected after that many rounds
+pub fn simulate(
+    notes: &[MonkeyNote],
+    rounds: usize,
+    rule: WorryRule,
+) -> Result<Vec<usize>, WorryError> {
+    let monkeys = run_rounds(notes, rounds, rule, &mut ())?;
+    Ok(monkeys.iter().map(Monkey::inspected).collect())
+}
+
+/// Product of the inspection counts of the n most active monkeys
+pub fn monkey_business(counts: &[usize], n: usize) -> usize {
+    let mut counts = counts.to_vec();
+    counts.sort_by_key(|c| std::cmp::Reverse(*c));
+    counts.iter().take(n).product()
+}
+
+/// Runs the rounds while recording everything that happens
+pub fn trace(notes: &[MonkeyNote], rounds: usize, rule: WorryRule) -> Result<Trace, WorryError> {
+    let mut trace = Trace::default();
+    run_rounds(notes, rounds, rule, &mut trace)?;
+    Ok(trace)
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    match b {
+        0 => a,
+        _ => gcd(b, a % b),
+    }
+}
+
+// Smallest number that all the divisors divide : as long as the operations only add,
+// subtract and multiply, reducing the worry levels modulo this doesn't change any test
+fn divisors_lcm(notes: &[MonkeyNote]) -> Result<i64, WorryError> {
+    notes
         .iter()
-        .map(|note| Monkey {
-            worry_levels: note.starting_items.clone(),
-            total_items: 0,
+        .map(|note| note.test_divisible_by)
+        .enumerate()
+        .try_fold(1i64, |lcm, (monkey, divisor)| {
+            (lcm / gcd(lcm, divisor))
+                .checked_mul(divisor)
+                .ok_or(WorryError::ModulusOverflow { monkey })
         })
-        .collect();
+}
 
-    for _ in 0..20 {
-        do_round(notes, &mut monkeys, |wl| wl / 3);
+fn check_modular(notes: &[MonkeyNote]) -> Result<(), WorryError> {
+    match notes.iter().position(|note| !note.operation.is_modular()) {
+        Some(monkey) => Err(WorryError::NotModular { monkey }),
+        None => Ok(()),
     }
+}
 
-    monkeys.sort_by(|a, b| b.total_items.cmp(&a.total_items));
-    monkeys.iter().take(2).map(|m| m.total_items).product()
+#[aoc(day11, part1)]
+fn part1(notes: &[MonkeyNote]) -> Result<usize, WorryError> {
+    let counts = simulate(notes, 20, WorryRule::Relief)?;
+    Ok(monkey_business(&counts, 2))
 }
 
 #[aoc(day11, part2)]
-fn part2(notes: &[MonkeyNote]) -> usize {
-    let mut monkeys: Vec<Monkey> = notes
-        .iter()
-        .map(|note| Monkey {
-            worry_levels: note.starting_items.clone(),
-            total_items: 0,
-        })
-        .collect();
+fn part2(notes: &[MonkeyNote]) -> Result<usize, WorryError> {
+    let counts = simulate(notes, 10000, WorryRule::Modulo)?;
+    Ok(monkey_business(&counts, 2))
+}
 
-    let modulus: i64 = notes.iter().map(|note| note.test_divisible_by).product();
-    for _ in 0..10000 {
-        do_round(notes, &mut monkeys, |wl| wl % modulus);
+/// Inspections of each monkey after that many rounds, with the worry levels kept modulo the
+/// divisors like in part 2.
+/// Items never affect each other, so we follow them one by one. An item only has so many
+/// possible states at the start of a round (which monkey holds it, and its worry level), so
+/// it ends up going around in a cycle, and we can skip all the rounds once we find it.
+pub fn inspections_by_cycles(
+    notes: &[MonkeyNote],
+    rounds: usize,
+) -> Result<Vec<usize>, WorryError> {
+    check_modular(notes)?;
+    let modulus = divisors_lcm(notes)?;
+    let mut totals = vec![0; notes.len()];
+    let mut seen = HashMap::new();
+    // Inspections of each monkey, after each round
+    let mut history: Vec<Vec<usize>> = Vec::new();
+
+    for (start_monkey, note) in notes.iter().enumerate() {
+        for (item, start_worry) in note.starting_items.iter().enumerate() {
+            seen.clear();
+            history.clear();
+            history.push(vec![0; notes.len()]);
+            let (mut monkey, mut worry) = (start_monkey, *start_worry);
+            let mut round = 0;
+            while round < rounds {
+                if let Some(cycle_start) = seen.insert((monkey, worry), round) {
+                    let cycle_len = round - cycle_start;
+                    let (cycles, rest) =
+                        ((rounds - round) / cycle_len, (rounds - round) % cycle_len);
+                    let (start, now) = (&history[cycle_start], &history[round]);
+                    for (m, total) in totals.iter_mut().enumerate() {
+                        *total += now[m]
+                            + cycles * (now[m] - start[m])
+                            + (history[cycle_start + rest][m] - start[m]);
+                    }
+                    break;
+                }
+
+                let mut counts = history[round].clone();
+                // The item is thrown until it lands on a monkey that already played this round
+                loop {
+                    counts[monkey] += 1;
+                    let note = &notes[monkey];
+                    worry = note
+                        .operation
+                        .eval(worry)
+                        .map_err(|err| err.inspecting(round + 1, monkey, item, worry))?
+                        % modulus;
+                    let target = match worry % note.test_divisible_by == 0 {
+                        true => note.if_true,
+                        false => note.if_false,
+                    };
+                    let next_round = target <= monkey;
+                    monkey = target;
+                    if next_round {
+                        break;
+                    }
+                }
+                history.push(counts);
+                round += 1;
+            }
+            if round == rounds {
+                for (total, count) in totals.iter_mut().zip(&history[rounds]) {
+                    *total += count;
+                }
+            }
+        }
     }
+    Ok(totals)
+}
+
+#[aoc(day11, part2, cycles)]
+fn part2_cycles(notes: &[MonkeyNote]) -> Result<usize, WorryError> {
+    let counts = inspections_by_cycles(notes, 10000)?;
+    Ok(monkey_business(&counts, 2))
+}
+
+// Same as part 1, printing what the monkeys hold after each round
+#[aoc(day11, part1, traced)]
+fn part1_traced(notes: &[MonkeyNote]) -> Result<usize, WorryError> {
+    eprintln!("{}\n", trace(notes, 20, WorryRule::Relief)?.summaries());
+    part1(notes)
+}
 
-    monkeys.sort_by(|a, b| b.total_items.cmp(&a.total_items));
-    monkeys.iter().take(2).map(|m| m.total_items).product()
+pub(crate) fn generator(input: &str) -> Result<Box<dyn Solver>, ParseError> {
+    let solver = DaySolver::new(
+        11,
+        parse(input)?,
+        |i| Ok(part1(i)?.into()),
+        |i| Ok(part2(i)?.into()),
+    );
+    Ok(Box::new(solver))
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
 
-    const INPUT: &'static str = "Monkey 0:
+    const INPUT: &str = "Monkey 0:
   Starting items: 79, 98
   Operation: new = old * 19
   Test: divisible by 23
@@ -191,8 +679,267 @@ Monkey 3:
 
     #[test]
     fn solve_day_11() {
-        let input = parse(INPUT);
-        assert_eq!(part1(&input), 10605);
-        assert_eq!(part2(&input), 2713310158);
+        let input = parse(INPUT).unwrap();
+        assert_eq!(part1(&input), Ok(10605));
+        assert_eq!(part2(&input), Ok(2713310158));
+    }
+
+    fn expr(text: &str) -> Result<Expr, ParseError> {
+        let line = Line {
+            day: 11,
+            number: 1,
+            text,
+        };
+        Expr::parse(line, text)
+    }
+
+    #[test]
+    fn day_11_expr() {
+        use Expr::*;
+        let old_times_19 = Binary(Box::new(Old), BinOp::Mul, Box::new(Literal(19)));
+        assert_eq!(expr("old * 19"), Ok(old_times_19));
+
+        let cases = [
+            ("old * old", 79 * 79),
+            ("old + 3 * 2", 85),
+            ("(old + 3) * 2", 164),
+            ("old - 9 - 10", 60),
+            ("old / 2 % 7", 4),
+            ("((old))%(10)", 9),
+            ("1000", 1000),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(expr(text).unwrap().eval(79), Ok(expected), "{text}");
+        }
+
+        let error = |column, expected| Err(ParseError::new(11, 1, column, expected));
+        assert_eq!(
+            expr("old ^ 2"),
+            error(5, "+, -, *, /, % or the end of the operation")
+        );
+        assert_eq!(expr("(old + 1"), error(9, "\")\""));
+        assert_eq!(expr("old +"), error(6, "\"old\", a number or \"(\""));
+        assert_eq!(expr("old * x"), error(7, "\"old\", a number or \"(\""));
+        assert_eq!(
+            expr("old 2"),
+            error(5, "+, -, *, /, % or the end of the operation")
+        );
+    }
+
+    #[test]
+    fn day_11_simulate() {
+        let input = parse(INPUT).unwrap();
+        let counts = simulate(&input, 20, WorryRule::Relief).unwrap();
+        assert_eq!(counts, vec![101, 95, 7, 105]);
+        assert_eq!(monkey_business(&counts, 2), 10605);
+
+        // Inspections given in the puzzle for part 2
+        let rounds = [
+            (1, vec![2, 4, 3, 6]),
+            (20, vec![99, 97, 8, 103]),
+            (1000, vec![5204, 4792, 199, 5192]),
+            (5000, vec![26075, 23921, 974, 26000]),
+            (10000, vec![52166, 47830, 1938, 52013]),
+        ];
+        for (rounds, expected) in rounds {
+            assert_eq!(simulate(&input, rounds, WorryRule::Modulo), Ok(expected));
+        }
+        assert_eq!(monkey_business(&[2, 4, 3, 6], 3), 72);
+
+        let err = simulate(&input, 20, WorryRule::Unchanged).unwrap_err();
+        assert!(matches!(err, WorryError::Overflow { monkey: 2, .. }));
+    }
+
+    #[test]
+    fn day_11_trace() {
+        let input = parse(INPUT).unwrap();
+        let trace = trace(&input, 20, WorryRule::Relief).unwrap();
+        assert_eq!(
+            trace.inspections[0],
+            Inspection {
+                round: 1,
+                monkey: 0,
+                old: 79,
+                new: 500,
+                divisible: false,
+                target: 3
+            }
+        );
+        // Monkey 2 squares 79, which is 6241, and gets 2080 after relief, divisible by 13
+        let inspection = trace.inspections[6];
+        assert_eq!((inspection.old, inspection.new), (79, 2080));
+        assert_eq!((inspection.divisible, inspection.target), (true, 1));
+
+        let summaries = trace.summaries();
+        let rounds: Vec<&str> = summaries.split("\n\n").collect();
+        assert_eq!(rounds.len(), 20);
+        assert_eq!(trace.round_summary(20).as_deref(), Some(rounds[19]));
+        assert_eq!(trace.round_summary(0), None);
+        assert_eq!(trace.round_summary(21), None);
+        assert_eq!(
+            rounds[0],
+            "After round 1, the monkeys are holding items with these worry levels:
+Monkey 0: 20, 23, 27, 26
+Monkey 1: 2080, 25, 167, 207, 401, 1046
+Monkey 2: 
+Monkey 3: "
+        );
+        assert_eq!(
+            rounds[19],
+            "After round 20, the monkeys are holding items with these worry levels:
+Monkey 0: 10, 12, 14, 26, 34
+Monkey 1: 245, 93, 53, 199, 115
+Monkey 2: 
+Monkey 3: "
+        );
+    }
+
+    #[test]
+    fn day_11_cycles() {
+        let input = parse(INPUT).unwrap();
+        let counts = inspections_by_cycles(&input, 10000).unwrap();
+        assert_eq!(counts, vec![52166, 47830, 1938, 52013]);
+        assert_eq!(part2_cycles(&input), part2(&input));
+
+        for rounds in [0, 1, 20, 1000, 1234] {
+            let expected = simulate(&input, rounds, WorryRule::Modulo).unwrap();
+            assert_eq!(inspections_by_cycles(&input, rounds).unwrap(), expected);
+        }
+
+        // Way too many rounds to run one by one
+        let counts = inspections_by_cycles(&input, 1_000_000_000).unwrap();
+        assert!(counts[0] > 5_000_000_000);
+    }
+
+    #[test]
+    fn day_11_overflow() {
+        let division_by_zero = Err(EvalError::DivisionByZero);
+        assert_eq!(expr("old / (old - 79)").unwrap().eval(79), division_by_zero);
+        assert_eq!(expr("old % (old - 79)").unwrap().eval(79), division_by_zero);
+        let overflow = Err(EvalError::Overflow);
+        assert_eq!(expr("old * 2").unwrap().eval(i64::MAX / 2 + 1), overflow);
+        assert_eq!(expr("old / (0 - 1)").unwrap().eval(i64::MIN), overflow);
+
+        let notes = parse(
+            "Monkey 0:
+  Starting items: 3, 2
+  Operation: new = old * old
+  Test: divisible by 2
+    If true: throw to monkey 0
+    If false: throw to monkey 0",
+        )
+        .unwrap();
+        let mut monkeys = vec![Monkey::new(&notes[0])];
+        let mut round = 1;
+        let err = loop {
+            if let Err(err) = do_round(&notes, &mut monkeys, round, |wl| wl, &mut ()) {
+                break err;
+            }
+            round += 1;
+        };
+        // 3^32 fits, but not its square
+        let expected = WorryError::Overflow {
+            round: 6,
+            monkey: 0,
+            item: 0,
+            worry_level: 3i64.pow(32),
+        };
+        assert_eq!(err, expected);
+
+        let notes = parse(&INPUT.replace("divisible by 23", "divisible by 26")).unwrap();
+        assert_eq!(divisors_lcm(&notes), Ok(26 * 19 * 17));
+
+        // Monkey 1 starts with an item of worry level 54
+        let notes = parse(&INPUT.replace("old + 6", "old / (old - 54)")).unwrap();
+        let err = simulate(&notes, 20, WorryRule::Relief).unwrap_err();
+        let expected = WorryError::DivisionByZero {
+            round: 1,
+            monkey: 1,
+            item: 0,
+        };
+        assert_eq!(err, expected);
+    }
+
+    #[test]
+    fn day_11_large_divisors() {
+        // Coprime, so the product of any 4 of them is too large to keep worry levels modulo it
+        let divisors = [1000003, 1000033, 1000037, 1000039, 1000081, 1000099];
+        let notes: Vec<String> = divisors
+            .iter()
+            .enumerate()
+            .map(|(monkey, divisor)| {
+                format!(
+                    "Monkey {monkey}:
+  Starting items: {}
+  Operation: new = old + {}
+  Test: divisible by {divisor}
+    If true: throw to monkey {}
+    If false: throw to monkey {}",
+                    monkey + 1,
+                    monkey * 3,
+                    (monkey + 1) % divisors.len(),
+                    (monkey + 2) % divisors.len()
+                )
+            })
+            .collect();
+        let notes = parse(&notes.join("\n\n")).unwrap();
+
+        assert!(part1(&notes).is_ok());
+        assert_eq!(
+            simulate(&notes, 5, WorryRule::Unchanged).map(|counts| counts.len()),
+            Ok(6)
+        );
+        let err = WorryError::ModulusOverflow { monkey: 3 };
+        assert_eq!(part2(&notes), Err(err.clone()));
+        assert_eq!(inspections_by_cycles(&notes, 20), Err(err));
+    }
+
+    #[test]
+    fn day_11_not_modular() {
+        // Keeping the worry levels modulo 6 would give [14, 4]
+        let notes = parse(
+            "Monkey 0:
+  Starting items: 2, 7
+  Operation: new = old * 3 / 2
+  Test: divisible by 3
+    If true: throw to monkey 1
+    If false: throw to monkey 0
+
+Monkey 1:
+  Starting items: 4
+  Operation: new = old + 1
+  Test: divisible by 2
+    If true: throw to monkey 0
+    If false: throw to monkey 0",
+        )
+        .unwrap();
+        assert_eq!(simulate(&notes, 5, WorryRule::Unchanged), Ok(vec![14, 9]));
+
+        let err = Err(WorryError::NotModular { monkey: 0 });
+        assert_eq!(simulate(&notes, 5, WorryRule::Modulo), err);
+        assert_eq!(inspections_by_cycles(&notes, 5), err);
+        assert!(expr("(old + 2) * old - 7").unwrap().is_modular());
+        assert!(!expr("old + (old % 5)").unwrap().is_modular());
+    }
+
+    #[test]
+    fn day_11_parse_error() {
+        let input = INPUT.replace("old + 6", "old ^ 6");
+        let err = parse(&input).unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::new(11, 10, 24, "+, -, *, /, % or the end of the operation")
+        );
+
+        let input = INPUT.replace("divisible by 19", "divisible by 0");
+        let err = parse(&input).unwrap_err();
+        assert_eq!(err, ParseError::new(11, 11, 22, "a divisor"));
+
+        let truncated = &INPUT[..INPUT.find("    If false: throw to monkey 1").unwrap()];
+        let err = parse(truncated).unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::new(11, 27, 1, "\"    If false: throw to monkey \"")
+        );
     }
 }
@@ -1,60 +1,227 @@
-use pathfinding::prelude::{bfs, Matrix};
+use std::cmp::Ordering;
 
-struct Heightmap {
+use pathfinding::prelude::{dijkstra, Matrix};
+
+use crate::error::{Line, ParseError};
+use crate::solver::{found, DaySolver, Solver};
+
+pub struct Heightmap {
     map: Matrix<u8>,
     start: (usize, usize),
     end: (usize, usize),
 }
 
+/// Where we can go from a square, and what it costs
+#[derive(Debug, Clone, Copy)]
+pub struct ClimbRules {
+    /// How much higher the next square can be, if there is a limit
+    pub max_ascent: Option<u8>,
+    /// How much lower the next square can be, if there is a limit
+    pub max_descent: Option<u8>,
+    /// Whether we can also move diagonally
+    pub diagonal: bool,
+    /// Cost of a step, from the change of elevation (going up is positive)
+    pub cost: fn(i32) -> usize,
+}
+
+impl ClimbRules {
+    /// Part 1, climbing from S to E : at most one elevation up, as far down as we want
+    pub const ASCENT: ClimbRules = ClimbRules {
+        max_ascent: Some(1),
+        max_descent: None,
+        diagonal: false,
+        cost: |_| 1,
+    };
+
+    /// Part 2, walking back from E to the closest a : the same moves, the other way round
+    pub const DESCENT: ClimbRules = ClimbRules {
+        max_ascent: None,
+        max_descent: Some(1),
+        diagonal: false,
+        cost: |_| 1,
+    };
+
+    // None if the step isn't allowed
+    fn step_cost(&self, from: u8, to: u8) -> Option<usize> {
+        let change = to as i32 - from as i32;
+        let too_high = self.max_ascent.is_some_and(|max| change > max as i32);
+        let too_low = self.max_descent.is_some_and(|max| -change > max as i32);
+        (!too_high && !too_low).then(|| (self.cost)(change))
+    }
+}
+
 #[aoc_generator(day12)]
-fn parse(input: &str) -> Heightmap {
-    let mut map = Matrix::from_rows(input.lines().map(str::bytes)).expect("Failed to build map");
+fn parse(input: &str) -> Result<Heightmap, ParseError> {
+    let lines: Vec<Line> = Line::all(12, input).collect();
+    let width = lines.first().map(|l| l.text.len()).unwrap_or(0);
+    for line in &lines {
+        if let Some(idx) = line
+            .text
+            .find(|c: char| !(c.is_ascii_lowercase() || c == 'S' || c == 'E'))
+        {
+            return Err(line.error(&line.text[idx..], "an elevation between a and z"));
+        }
+        if line.text.len() != width {
+            return Err(line.error_at_end(format!("a row of {width} squares")));
+        }
+    }
+    let missing = |expected: &str| ParseError::new(12, lines.len() + 1, 1, expected);
+    let mut map = Matrix::from_rows(lines.iter().map(|l| l.text.bytes()))
+        .map_err(|_| missing("a heightmap"))?;
     // I'm very sad we can't find those in one pass (when building the Matrix),
     // but I couldn't find how!
-    let start = map.keys().find(|p| map[*p] == b'S').unwrap();
-    let end = map.keys().find(|p| map[*p] == b'E').unwrap();
+    let start = map
+        .keys()
+        .find(|p| map[*p] == b'S')
+        .ok_or_else(|| missing("a start position S"))?;
+    let end = map
+        .keys()
+        .find(|p| map[*p] == b'E')
+        .ok_or_else(|| missing("a best signal position E"))?;
     map[start] = b'a';
     map[end] = b'z';
-    Heightmap { map, start, end }
+    Ok(Heightmap { map, start, end })
 }
 
+impl Heightmap {
+    pub fn parse(input: &str) -> Result<Heightmap, ParseError> {
+        parse(input)
+    }
+
+    /// Where we are, marked S
+    pub fn start(&self) -> (usize, usize) {
+        self.start
+    }
+
+    /// The location with the best signal, marked E
+    pub fn end(&self) -> (usize, usize) {
+        self.end
+    }
+
+    /// Elevation of a square, from 'a' to 'z' (S is at a, and E at z). None outside the map.
+    pub fn elevation(&self, square: (usize, usize)) -> Option<char> {
+        self.map.get(square).map(|e| *e as char)
+    }
+
+    /// Cheapest route from a square to the first one that is_goal accepts, both included,
+    /// along with its cost
+    pub fn cheapest_route(
+        &self,
+        from: (usize, usize),
+        is_goal: impl Fn((usize, usize)) -> bool,
+        rules: &ClimbRules,
+    ) -> Option<(Vec<(usize, usize)>, usize)> {
+        dijkstra(
+            &from,
+            |&p| {
+                self.map
+                    .neighbours(p, rules.diagonal)
+                    .filter_map(move |n| rules.step_cost(self.map[p], self.map[n]).map(|c| (n, c)))
+            },
+            |&p| is_goal(p),
+        )
+    }
+
+    /// Shortest route from S to the best signal E, both included
+    pub fn route_to_end(&self) -> Option<Vec<(usize, usize)>> {
+        self.cheapest_route(self.start, |p| p == self.end, &ClimbRules::ASCENT)
+            .map(|(route, _)| route)
+    }
+
+    /// Shortest route from any square at elevation a to E, both included
+    pub fn route_from_lowest(&self) -> Option<Vec<(usize, usize)>> {
+        // Going down from E, so that we find the closest a
+        let is_lowest = |p| self.map[p] == b'a';
+        let (mut route, _) = self.cheapest_route(self.end, is_lowest, &ClimbRules::DESCENT)?;
+        route.reverse();
+        Some(route)
+    }
+
+    /// The route over the heightmap, like in the puzzle : each square points to the next one,
+    /// E is the end, and squares off the route are dots. Diagonal steps are drawn with arrows
+    /// since the puzzle doesn't have any
+    pub fn render_route(&self, route: &[(usize, usize)]) -> String {
+        let mut grid = Matrix::new(self.map.rows, self.map.columns, '.');
+        for step in route.windows(2) {
+            let ((row, column), (next_row, next_column)) = (step[0], step[1]);
+            grid[step[0]] = match (next_row.cmp(&row), next_column.cmp(&column)) {
+                (Ordering::Less, Ordering::Less) => '↖',
+                (Ordering::Less, Ordering::Greater) => '↗',
+                (Ordering::Greater, Ordering::Less) => '↙',
+                (Ordering::Greater, Ordering::Greater) => '↘',
+                (Ordering::Less, _) => '^',
+                (Ordering::Greater, _) => 'v',
+                (_, Ordering::Less) => '<',
+                _ => '>',
+            };
+        }
+        if let Some(last) = route.last() {
+            grid[*last] = 'E';
+        }
+        grid.iter()
+            .map(|row| row.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// PPM image of the heightmap, getting lighter as the elevation goes up, with the route
+    /// in red. Each square is `scale` pixels wide.
+    pub fn route_ppm(&self, route: &[(usize, usize)], scale: usize) -> Vec<u8> {
+        let mut colors = self.map.clone().map(|elevation| {
+            let shade = 40 + (elevation - b'a') * 8;
+            [shade / 2, shade, shade / 2]
+        });
+        for p in route {
+            colors[*p] = [220, 40, 40];
+        }
+        for p in [self.start, self.end] {
+            colors[p] = [255, 220, 0];
+        }
+
+        let (width, height) = (self.map.columns * scale, self.map.rows * scale);
+        let mut ppm = format!("P6\n{width} {height}\n255\n").into_bytes();
+        for row in colors.iter() {
+            let line: Vec<u8> = row
+                .iter()
+                .flat_map(|color| std::iter::repeat_n(color, scale).flatten())
+                .copied()
+                .collect();
+            for _ in 0..scale {
+                ppm.extend(&line);
+            }
+        }
+        ppm
+    }
+}
+
+// None if the end can't be reached
 #[aoc(day12, part1)]
-fn part1(input: &Heightmap) -> usize {
-    let path = bfs(
-        &input.start,
-        |&p| {
-            input
-                .map
-                .neighbours(p, false)
-                .filter(move |n| input.map[p] + 1 >= input.map[*n])
-        },
-        |&p| p == input.end,
-    )
-    .expect("Failed to find path");
-    path.len() - 1
+fn part1(input: &Heightmap) -> Option<usize> {
+    let path = input.route_to_end()?;
+    Some(path.len() - 1)
 }
 
 #[aoc(day12, part2)]
-fn part2(input: &Heightmap) -> usize {
-    let path = bfs(
-        &input.end,
-        |&p| {
-            input
-                .map
-                .neighbours(p, false)
-                .filter(move |n| input.map[p] <= input.map[*n] + 1)
-        },
-        |&p| input.map[p] == b'a',
-    )
-    .expect("Failed to find path");
-    path.len() - 1
+fn part2(input: &Heightmap) -> Option<usize> {
+    let path = input.route_from_lowest()?;
+    Some(path.len() - 1)
+}
+
+pub(crate) fn generator(input: &str) -> Result<Box<dyn Solver>, ParseError> {
+    let solver = DaySolver::new(
+        12,
+        parse(input)?,
+        |i| found(12, 1, part1(i)),
+        |i| found(12, 2, part2(i)),
+    );
+    Ok(Box::new(solver))
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
 
-    const INPUT: &'static str = "Sabqponm
+    const INPUT: &str = "Sabqponm
 abcryxxl
 accszExk
 acctuvwj
@@ -62,8 +229,178 @@ abdefghi";
 
     #[test]
     fn solve_day_12() {
-        let input = parse(INPUT);
-        assert_eq!(part1(&input), 31);
-        assert_eq!(part2(&input), 29);
+        let input = parse(INPUT).unwrap();
+        assert_eq!(part1(&input), Some(31));
+        assert_eq!(part2(&input), Some(29));
+
+        let input = parse("SzE").unwrap();
+        assert_eq!(part1(&input), None);
+        assert_eq!(part2(&input), None);
+    }
+
+    // Checks that each step of the route follows the rules, and returns its cost
+    fn route_cost(input: &Heightmap, route: &[(usize, usize)], rules: &ClimbRules) -> usize {
+        route
+            .windows(2)
+            .map(|step| {
+                let (a, b) = (step[0], step[1]);
+                let (dr, dc) = (a.0.abs_diff(b.0), a.1.abs_diff(b.1));
+                assert!(dr <= 1 && dc <= 1 && (rules.diagonal || dr + dc == 1));
+                rules.step_cost(input.map[a], input.map[b]).unwrap()
+            })
+            .sum()
+    }
+
+    #[test]
+    fn day_12_climb_rules() {
+        let input = parse(INPUT).unwrap();
+        let to_end = |p| p == input.end();
+
+        let (route, cost) = input
+            .cheapest_route(input.start(), to_end, &ClimbRules::ASCENT)
+            .unwrap();
+        assert_eq!(cost, 31);
+        assert_eq!(route_cost(&input, &route, &ClimbRules::ASCENT), 31);
+
+        // Without any limit, the best route is as the crow flies
+        let anywhere = ClimbRules {
+            max_ascent: None,
+            diagonal: true,
+            ..ClimbRules::ASCENT
+        };
+        let (_, cost) = input
+            .cheapest_route(input.start(), to_end, &anywhere)
+            .unwrap();
+        assert_eq!(cost, 5);
+
+        let diagonal = ClimbRules {
+            diagonal: true,
+            ..ClimbRules::ASCENT
+        };
+        let (route, cost) = input
+            .cheapest_route(input.start(), to_end, &diagonal)
+            .unwrap();
+        assert_eq!(route_cost(&input, &route, &diagonal), cost);
+        assert!(cost < 31);
+
+        // Climbing is tiring : 1 per step, and 10 more per elevation up
+        let tiring = ClimbRules {
+            max_ascent: Some(2),
+            cost: |change| 1 + 10 * change.max(0) as usize,
+            ..ClimbRules::ASCENT
+        };
+        let (route, cost) = input
+            .cheapest_route(input.start(), to_end, &tiring)
+            .unwrap();
+        assert_eq!(route_cost(&input, &route, &tiring), cost);
+        // We have to go up 25 elevations anyway
+        assert!(cost >= 250 + route.len() - 1);
+
+        // The spiral of the example never goes down
+        let careful = ClimbRules {
+            max_descent: Some(0),
+            ..ClimbRules::ASCENT
+        };
+        let (_, cost) = input
+            .cheapest_route(input.start(), to_end, &careful)
+            .unwrap();
+        assert_eq!(cost, 31);
+        let flat = ClimbRules {
+            max_ascent: Some(0),
+            ..ClimbRules::ASCENT
+        };
+        assert_eq!(input.cheapest_route(input.start(), to_end, &flat), None);
+
+        // Part 2 with diagonals, walking back from E like route_from_lowest
+        let descent = ClimbRules {
+            diagonal: true,
+            ..ClimbRules::DESCENT
+        };
+        let is_lowest = |p| input.elevation(p) == Some('a');
+        let (route, cost) = input
+            .cheapest_route(input.end(), is_lowest, &descent)
+            .unwrap();
+        assert_eq!(route_cost(&input, &route, &descent), cost);
+        assert!(cost < 29);
+        assert_eq!(input.elevation(*route.last().unwrap()), Some('a'));
+        assert_eq!(input.elevation(input.end()), Some('z'));
+        assert_eq!(input.elevation((5, 0)), None);
+    }
+
+    #[test]
+    fn day_12_render() {
+        let input = parse(INPUT).unwrap();
+        let route = input.route_to_end().unwrap();
+        assert_eq!(route.first(), Some(&input.start));
+        // Not the route drawn in the puzzle, but just as short
+        assert_eq!(
+            input.render_route(&route),
+            ">>vv<<<<
+..vvv<<^
+..vv>E^^
+..v>>>^^
+..>>>>>^"
+        );
+
+        // Without any limit, we go straight to E
+        let anywhere = ClimbRules {
+            max_ascent: None,
+            diagonal: true,
+            ..ClimbRules::ASCENT
+        };
+        let (route, _) = input
+            .cheapest_route(input.start, |p| p == input.end, &anywhere)
+            .unwrap();
+        assert_eq!(
+            input.render_route(&route),
+            ">>↘.....
+...↘....
+....>E..
+........
+........"
+        );
+        let zigzag = [(1, 1), (0, 2), (1, 3), (2, 2), (3, 1), (2, 0)];
+        assert_eq!(
+            input.render_route(&zigzag),
+            "..↘.....
+.↗.↙....
+E.↙.....
+.↖......
+........"
+        );
+
+        let route = input.route_from_lowest().unwrap();
+        assert_eq!(input.map[route[0]], b'a');
+        assert_eq!(route.last(), Some(&input.end));
+
+        let ppm = input.route_ppm(&route, 2);
+        let header = b"P6\n16 10\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+        assert_eq!(ppm.len(), header.len() + 16 * 10 * 3);
+        // S and E stand out, even when the route doesn't go through them
+        assert_eq!(
+            ppm[header.len()..header.len() + 6],
+            [255, 220, 0, 255, 220, 0]
+        );
+    }
+
+    #[test]
+    fn day_12_parse_error() {
+        assert_eq!(
+            parse("Sab\naBE").err(),
+            Some(ParseError::new(12, 2, 2, "an elevation between a and z"))
+        );
+        assert_eq!(
+            parse("Sab\nabEc").err(),
+            Some(ParseError::new(12, 2, 5, "a row of 3 squares"))
+        );
+        assert_eq!(
+            parse("abc\nabE").err(),
+            Some(ParseError::new(12, 3, 1, "a start position S"))
+        );
+        assert_eq!(
+            parse("Sbc").err(),
+            Some(ParseError::new(12, 2, 1, "a best signal position E"))
+        );
     }
 }
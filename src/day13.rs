@@ -0,0 +1,165 @@
+use std::cmp::Ordering;
+
+use crate::error::{Line, ParseError};
+use crate::solver::{DaySolver, Solver};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Packet {
+    Integer(u32),
+    List(Vec<Packet>),
+}
+
+impl Packet {
+    fn parse(line: Line) -> Result<Packet, ParseError> {
+        let (packet, rest) = Packet::parse_one(line, line.text)?;
+        if !rest.is_empty() {
+            return Err(line.error(rest, "end of packet"));
+        }
+        Ok(packet)
+    }
+
+    // Parses a single packet at the start of input, and returns what's left after it
+    fn parse_one<'a>(line: Line, input: &'a str) -> Result<(Packet, &'a str), ParseError> {
+        if let Some(mut rest) = input.strip_prefix('[') {
+            let mut items = Vec::new();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((Packet::List(items), after));
+            }
+            loop {
+                let (item, after) = Packet::parse_one(line, rest)?;
+                items.push(item);
+                if let Some(after) = after.strip_prefix(',') {
+                    rest = after;
+                } else if let Some(after) = after.strip_prefix(']') {
+                    return Ok((Packet::List(items), after));
+                } else {
+                    return Err(line.error(after, "',' or ']'"));
+                }
+            }
+        }
+        let len = input.bytes().take_while(|c| c.is_ascii_digit()).count();
+        let value = line.parse(&input[..len], "'[' or an integer")?;
+        Ok((Packet::Integer(value), &input[len..]))
+    }
+
+    fn divider(value: u32) -> Packet {
+        Packet::List(vec![Packet::List(vec![Packet::Integer(value)])])
+    }
+}
+
+impl Ord for Packet {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Packet::Integer(a), Packet::Integer(b)) => a.cmp(b),
+            (Packet::List(a), Packet::List(b)) => a.cmp(b),
+            // Mixed types : the integer gets wrapped in a list of one item
+            (Packet::Integer(a), Packet::List(b)) => [Packet::Integer(*a)][..].cmp(b),
+            (Packet::List(a), Packet::Integer(b)) => a[..].cmp(&[Packet::Integer(*b)]),
+        }
+    }
+}
+
+impl PartialOrd for Packet {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[aoc_generator(day13)]
+fn parse(input: &str) -> Result<Vec<Packet>, ParseError> {
+    let packets: Vec<Packet> = Line::all(13, input)
+        .filter(|l| !l.text.is_empty())
+        .map(Packet::parse)
+        .collect::<Result<_, _>>()?;
+    if !packets.len().is_multiple_of(2) {
+        let line = input.lines().count() + 1;
+        return Err(ParseError::new(
+            13,
+            line,
+            1,
+            "the second packet of the pair",
+        ));
+    }
+    Ok(packets)
+}
+
+#[aoc(day13, part1)]
+fn part1(input: &[Packet]) -> usize {
+    input
+        .chunks(2)
+        .enumerate()
+        .filter(|(_idx, pair)| pair[0] < pair[1])
+        .map(|(idx, _pair)| idx + 1)
+        .sum()
+}
+
+#[aoc(day13, part2)]
+fn part2(input: &[Packet]) -> usize {
+    let divider_two = Packet::divider(2);
+    let divider_six = Packet::divider(6);
+    // No need to sort, we only need to know how many packets come before each divider
+    let before_two = input.iter().filter(|p| **p < divider_two).count() + 1;
+    let before_six = input.iter().filter(|p| **p < divider_six).count() + 2;
+    before_two * before_six
+}
+
+pub(crate) fn generator(input: &str) -> Result<Box<dyn Solver>, ParseError> {
+    let solver = DaySolver::new(
+        13,
+        parse(input)?,
+        |i| Ok(part1(i).into()),
+        |i| Ok(part2(i).into()),
+    );
+    Ok(Box::new(solver))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    const INPUT: &str = "[1,1,3,1,1]
+[1,1,5,1,1]
+
+[[1],[2,3,4]]
+[[1],4]
+
+[9]
+[[8,7,6]]
+
+[[4,4],4,4]
+[[4,4],4,4,4]
+
+[7,7,7,7]
+[7,7,7]
+
+[]
+[3]
+
+[[[]]]
+[[]]
+
+[1,[2,[3,[4,[5,6,7]]]],8,9]
+[1,[2,[3,[4,[5,6,0]]]],8,9]";
+
+    #[test]
+    fn solve_day_13() {
+        let input = parse(INPUT).unwrap();
+        assert_eq!(part1(&input), 13);
+        assert_eq!(part2(&input), 140);
+    }
+
+    #[test]
+    fn day_13_parse_error() {
+        let err = parse("[1,[2]]\n[1,2]]").unwrap_err();
+        assert_eq!(err, ParseError::new(13, 2, 6, "end of packet"));
+        let err = parse("[1,[2]]\n[1;2]").unwrap_err();
+        assert_eq!(err, ParseError::new(13, 2, 3, "',' or ']'"));
+        let err = parse("[1,x]\n[1]").unwrap_err();
+        assert_eq!(err, ParseError::new(13, 1, 4, "'[' or an integer"));
+        let err = parse("[1]\n[2]\n\n[3]").unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::new(13, 5, 1, "the second packet of the pair")
+        );
+    }
+}
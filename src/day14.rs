@@ -1,6 +1,13 @@
-use aoc_parse::prelude::*;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
 use pathfinding::prelude::Matrix;
 
+use crate::error::{Line, ParseError};
+use crate::solver::{DaySolver, Solver};
+
 #[derive(Debug, Clone, Copy)]
 enum BlockType {
     Air,
@@ -8,57 +15,121 @@ enum BlockType {
     Sand,
 }
 
+impl BlockType {
+    fn symbol(self) -> char {
+        match self {
+            BlockType::Air => '.',
+            BlockType::Rock => '#',
+            BlockType::Sand => 'o',
+        }
+    }
+
+    fn color(self) -> [u8; 3] {
+        match self {
+            BlockType::Air => [20, 20, 30],
+            BlockType::Rock => [110, 110, 110],
+            BlockType::Sand => [230, 190, 90],
+        }
+    }
+}
+
 struct Point {
     pub x: usize,
     pub y: usize,
 }
 
-struct ScanResult {
+#[derive(Debug)]
+pub struct ScanResult {
     map: Matrix<BlockType>,
     max_height: usize,
+    /// Where the sand comes from, in map coordinates
+    source: (usize, usize),
+}
+
+const SOURCE_X: usize = 500;
+
+// The map grows with the square of the coordinates, so we don't go too far
+const MAX_COORDINATE: usize = 5000;
+
+fn parse_point(line: Line, text: &str) -> Result<Point, ParseError> {
+    let (x, y) = text
+        .split_once(',')
+        .ok_or_else(|| line.error(text, "a point like x,y"))?;
+    let point = Point {
+        x: line.parse(x, "a x coordinate")?,
+        y: line.parse(y, "a y coordinate")?,
+    };
+    if point.x > MAX_COORDINATE || point.y > MAX_COORDINATE {
+        return Err(line.error(text, format!("coordinates up to {MAX_COORDINATE}")));
+    }
+    Ok(point)
 }
 
 #[aoc_generator(day14)]
-fn parse(input: &str) -> ScanResult {
-    let point = parser!(
-        x:usize "," y:usize
-        => Point { x, y }
-    );
-    let structure = parser!(repeat_sep(point, " -> "));
-    let structures = parser!(lines(structure));
+fn parse(input: &str) -> Result<ScanResult, ParseError> {
+    let mut structures = Vec::new();
+    for line in Line::all(14, input) {
+        let mut structure: Vec<Point> = Vec::new();
+        for text in line.text.split(" -> ") {
+            let point = parse_point(line, text)?;
+            if let Some(previous) = structure.last() {
+                if previous.x != point.x && previous.y != point.y {
+                    return Err(line.error(text, "a point in line with the previous one"));
+                }
+            }
+            structure.push(point);
+        }
+        structures.push(structure);
+    }
+
+    let max_height = structures.iter().flatten().map(|p| p.y).max().unwrap_or(0) + 2;
+    // In part 2, the sand piles up in a triangle that stops right above the floor,
+    // and the last grains need to look one block further on each side.
+    // Rocks can be even further, and we keep x as isize in here since the
+    // triangle can go below 0.
+    let pyramid_left = SOURCE_X as isize - max_height as isize;
+    let pyramid_right = (SOURCE_X + max_height) as isize;
+    let left = structures
+        .iter()
+        .flatten()
+        .map(|p| p.x as isize)
+        .fold(pyramid_left, isize::min);
+    let right = structures
+        .iter()
+        .flatten()
+        .map(|p| p.x as isize)
+        .fold(pyramid_right, isize::max);
+    let column = |x: usize| (x as isize - left) as usize;
 
-    let mut map = Matrix::new(700, 200, BlockType::Air);
-    let mut max_height = 0;
-    let parsed = structures.parse(input).unwrap();
-    for l in parsed.into_iter() {
+    let mut map = Matrix::new((right - left + 1) as usize, max_height + 1, BlockType::Air);
+    for l in structures.into_iter() {
         for (a, b) in l.iter().zip(l.iter().skip(1)) {
-            max_height = max_height.max(a.y).max(b.y);
             let min_y = a.y.min(b.y);
             let min_x = a.x.min(b.x);
             if a.x == b.x {
                 // Vertical slice
                 let max_y = a.y.max(b.y);
                 for y in min_y..=max_y {
-                    map[(a.x, y)] = BlockType::Rock;
+                    map[(column(a.x), y)] = BlockType::Rock;
                 }
             } else {
                 // Horizontal slice
                 let max_x = a.x.max(b.x);
                 for x in min_x..=max_x {
-                    map[(x, a.y)] = BlockType::Rock;
+                    map[(column(x), a.y)] = BlockType::Rock;
                 }
             };
         }
     }
-    ScanResult {
+    Ok(ScanResult {
         map,
-        max_height: max_height + 2,
-    }
+        max_height,
+        source: (column(SOURCE_X), 0),
+    })
 }
 
-fn spawn_sand(map: &Matrix<BlockType>) -> Option<(usize, usize)> {
-    let mut x = 500;
-    let mut y = 0;
+fn spawn_sand(map: &Matrix<BlockType>, source: (usize, usize)) -> Option<(usize, usize)> {
+    let (mut x, mut y) = source;
     loop {
         let below = map.move_in_direction((x, y), (0, 1))?;
         let block = map[below];
@@ -96,54 +167,401 @@ fn spawn_sand(map: &Matrix<BlockType>) -> Option<(usize, usize)> {
     }
 }
 
-#[aoc(day14, part1)]
-fn part1(input: &ScanResult) -> usize {
-    let mut map = input.map.clone();
-    (0..)
-        .find(|_| match spawn_sand(&map) {
-            Some((x, y)) => {
-                map[(x, y)] = BlockType::Sand;
-                false
+/// How grains are dropped in the simulation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandMethod {
+    /// Every grain falls all the way from the source, like in the puzzle
+    Replay,
+    /// Each grain starts from the last free position on the path of the previous one
+    Trail,
+}
+
+// Drops grains from the source until one falls out of the map, or the source gets blocked.
+// Returns how many came to rest.
+fn pour_from_source(map: &mut Matrix<BlockType>, source: (usize, usize)) -> usize {
+    let mut resting = 0;
+    while let Some(position) = spawn_sand(map, source) {
+        map[position] = BlockType::Sand;
+        resting += 1;
+        if position == source {
+            break;
+        }
+    }
+    resting
+}
+
+// Same as pour_from_source, but we remember the path of the falling grain. When it comes
+// to rest, the next one follows the exact same path until the position right before it,
+// so it can start from there instead.
+fn pour_with_trail(map: &mut Matrix<BlockType>, source: (usize, usize)) -> usize {
+    let mut trail = vec![source];
+    let mut resting = 0;
+    while let Some(&position) = trail.last() {
+        let mut next = None;
+        for direction in [(0, 1), (-1, 1), (1, 1)] {
+            match map.move_in_direction(position, direction) {
+                // Falls into the abyss, and so will every grain after it
+                None => return resting,
+                Some(p) if matches!(map[p], BlockType::Air) => {
+                    next = Some(p);
+                    break;
+                }
+                Some(_) => (),
             }
-            None => true,
-        })
-        .unwrap()
+        }
+        match next {
+            Some(p) => trail.push(p),
+            None => {
+                map[position] = BlockType::Sand;
+                resting += 1;
+                trail.pop();
+            }
+        }
+    }
+    resting
 }
 
-#[aoc(day14, part2)]
-fn part2(input: &ScanResult) -> usize {
-    let ScanResult { map, max_height } = input;
-    let mut map = map.clone();
-    // Add a floor
-    for x in 0usize..700usize {
-        map[(x, *max_height)] = BlockType::Rock;
-    }
-
-    (1..)
-        .find(|_| match spawn_sand(&map) {
-            Some((x, y)) => {
-                if x == 500 && y == 0 {
-                    return true;
+fn pour(map: &mut Matrix<BlockType>, source: (usize, usize), method: SandMethod) -> usize {
+    match method {
+        SandMethod::Replay => pour_from_source(map, source),
+        SandMethod::Trail => pour_with_trail(map, source),
+    }
+}
+
+impl ScanResult {
+    pub fn parse(input: &str) -> Result<ScanResult, ParseError> {
+        parse(input)
+    }
+
+    fn with_floor(&self) -> Matrix<BlockType> {
+        let mut map = self.map.clone();
+        for x in 0..map.rows {
+            map[(x, self.max_height)] = BlockType::Rock;
+        }
+        map
+    }
+
+    /// Grains that come to rest before the sand starts falling into the abyss (part 1)
+    pub fn sand_into_abyss(&self, method: SandMethod) -> usize {
+        pour(&mut self.map.clone(), self.source, method)
+    }
+
+    /// Grains that come to rest on the floor until the source is blocked (part 2)
+    pub fn sand_on_floor(&self, method: SandMethod) -> usize {
+        pour(&mut self.with_floor(), self.source, method)
+    }
+
+    /// Same as sand_on_floor without simulating anything : with a floor, the sand ends up
+    /// everywhere it can reach, so we count those positions row by row
+    pub fn sand_on_floor_by_rows(&self) -> usize {
+        let width = self.map.rows;
+        let mut reachable = vec![false; width];
+        let mut next = vec![false; width];
+        reachable[self.source.0] = true;
+        let mut count = 1;
+        for y in 1..self.max_height {
+            for x in 0..width {
+                let from_above = reachable[x]
+                    || (x > 0 && reachable[x - 1])
+                    || (x + 1 < width && reachable[x + 1]);
+                next[x] = from_above && matches!(self.map[(x, y)], BlockType::Air);
+            }
+            count += next.iter().filter(|r| **r).count();
+            std::mem::swap(&mut reachable, &mut next);
+        }
+        count
+    }
+}
+
+/// Where the frames of an animation go
+#[derive(Debug, Clone)]
+pub enum FrameOutput {
+    /// Printed one after the other on stdout
+    Terminal,
+    /// One frame_NNNNN.txt per frame, in that directory
+    Text(PathBuf),
+    /// One frame_NNNNN.ppm per frame, in that directory, with a pixel per block
+    Ppm(PathBuf),
+}
+
+// The part of the map worth drawing : everything that isn't air, and the source.
+// The floor goes all the way on both sides, so it's only drawn below the rest.
+#[derive(Debug, Clone, Copy)]
+struct Crop {
+    left: usize,
+    right: usize,
+    bottom: usize,
+}
+
+impl Crop {
+    fn around(map: &Matrix<BlockType>, source: (usize, usize), floor: Option<usize>) -> Crop {
+        let mut crop = Crop {
+            left: source.0,
+            right: source.0,
+            bottom: source.1,
+        };
+        for x in 0..map.rows {
+            for y in 0..map.columns {
+                if floor == Some(y) || matches!(map[(x, y)], BlockType::Air) {
+                    continue;
                 }
-                map[(x, y)] = BlockType::Sand;
-                false
+                crop.left = crop.left.min(x);
+                crop.right = crop.right.max(x);
+                crop.bottom = crop.bottom.max(y);
             }
-            None => true,
+        }
+        if let Some(floor) = floor {
+            crop.bottom = floor;
+        }
+        crop
+    }
+
+    fn width(&self) -> usize {
+        self.right - self.left + 1
+    }
+
+    fn height(&self) -> usize {
+        self.bottom + 1
+    }
+}
+
+fn draw_text(map: &Matrix<BlockType>, source: (usize, usize), crop: Crop) -> String {
+    (0..crop.height())
+        .map(|y| {
+            (crop.left..=crop.right)
+                .map(|x| match map[(x, y)] {
+                    BlockType::Air if (x, y) == source => '+',
+                    block => block.symbol(),
+                })
+                .collect::<String>()
         })
-        .unwrap()
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn draw_ppm(map: &Matrix<BlockType>, source: (usize, usize), crop: Crop) -> Vec<u8> {
+    let mut ppm = format!("P6\n{} {}\n255\n", crop.width(), crop.height()).into_bytes();
+    for y in 0..crop.height() {
+        for x in crop.left..=crop.right {
+            ppm.extend(match map[(x, y)] {
+                BlockType::Air if (x, y) == source => [220, 40, 40],
+                block => block.color(),
+            });
+        }
+    }
+    ppm
+}
+
+fn write_frame(
+    map: &Matrix<BlockType>,
+    source: (usize, usize),
+    crop: Crop,
+    output: &FrameOutput,
+    (frame, resting): (usize, usize),
+) -> io::Result<()> {
+    match output {
+        FrameOutput::Terminal => {
+            println!(
+                "After {resting} grains:\n{}\n",
+                draw_text(map, source, crop)
+            );
+            Ok(())
+        }
+        FrameOutput::Text(dir) => fs::write(
+            dir.join(format!("frame_{frame:05}.txt")),
+            draw_text(map, source, crop),
+        ),
+        FrameOutput::Ppm(dir) => fs::write(
+            dir.join(format!("frame_{frame:05}.ppm")),
+            draw_ppm(map, source, crop),
+        ),
+    }
+}
+
+impl ScanResult {
+    /// Drops grains one by one like in the puzzle, with the floor of part 2 or not, and
+    /// outputs the cave every `every` grains (and once more at the end).
+    /// Returns how many grains came to rest.
+    pub fn animate(&self, floor: bool, every: usize, output: &FrameOutput) -> io::Result<usize> {
+        let (mut map, floor) = match floor {
+            true => (self.with_floor(), Some(self.max_height)),
+            false => (self.map.clone(), None),
+        };
+        // All the frames have the same size : the one of the cave once it's full
+        let mut full = map.clone();
+        pour_from_source(&mut full, self.source);
+        let crop = Crop::around(&full, self.source, floor);
+        if let FrameOutput::Text(dir) | FrameOutput::Ppm(dir) = output {
+            fs::create_dir_all(dir)?;
+        }
+
+        let every = every.max(1);
+        let mut frame = 0;
+        let mut resting = 0;
+        write_frame(&map, self.source, crop, output, (frame, resting))?;
+        while let Some(position) = spawn_sand(&map, self.source) {
+            map[position] = BlockType::Sand;
+            resting += 1;
+            if resting.is_multiple_of(every) {
+                frame += 1;
+                write_frame(&map, self.source, crop, output, (frame, resting))?;
+            }
+            if position == self.source {
+                break;
+            }
+        }
+        if !resting.is_multiple_of(every) {
+            write_frame(&map, self.source, crop, output, (frame + 1, resting))?;
+        }
+        Ok(resting)
+    }
+}
+
+impl fmt::Display for ScanResult {
+    /// The cave as it was scanned, cropped around the rocks and the source
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let crop = Crop::around(&self.map, self.source, None);
+        write!(f, "{}", draw_text(&self.map, self.source, crop))
+    }
+}
+
+#[aoc(day14, part1)]
+fn part1(input: &ScanResult) -> usize {
+    input.sand_into_abyss(SandMethod::Trail)
+}
+
+#[aoc(day14, part1, replay)]
+fn part1_replay(input: &ScanResult) -> usize {
+    input.sand_into_abyss(SandMethod::Replay)
+}
+
+#[aoc(day14, part2)]
+fn part2(input: &ScanResult) -> usize {
+    input.sand_on_floor(SandMethod::Trail)
+}
+
+#[aoc(day14, part2, replay)]
+fn part2_replay(input: &ScanResult) -> usize {
+    input.sand_on_floor(SandMethod::Replay)
+}
+
+#[aoc(day14, part2, rows)]
+fn part2_rows(input: &ScanResult) -> usize {
+    input.sand_on_floor_by_rows()
+}
+
+pub(crate) fn generator(input: &str) -> Result<Box<dyn Solver>, ParseError> {
+    let solver = DaySolver::new(
+        14,
+        parse(input)?,
+        |i| Ok(part1(i).into()),
+        |i| Ok(part2(i).into()),
+    );
+    Ok(Box::new(solver))
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use crate::testing::Xorshift;
 
-    const INPUT: &'static str = "498,4 -> 498,6 -> 496,6
+    const INPUT: &str = "498,4 -> 498,6 -> 496,6
 503,4 -> 502,4 -> 502,9 -> 494,9";
 
     #[test]
     fn solve_day_14() {
-        let input = parse(INPUT);
+        let input = parse(INPUT).unwrap();
         assert_eq!(part1(&input), 24);
         assert_eq!(part2(&input), 93);
     }
+
+    #[test]
+    fn day_14_methods_agree() {
+        let mut random = Xorshift::new(14);
+        let mut inputs = vec![INPUT.to_string()];
+        for _ in 0..10 {
+            let paths = (0..8).map(|_| {
+                let (x, y) = (490 + random.below(20), 2 + random.below(20));
+                match random.below(2) {
+                    0 => format!("{x},{y} -> {},{y}", x + random.below(6)),
+                    _ => format!("{x},{y} -> {x},{}", y + random.below(6)),
+                }
+            });
+            inputs.push(paths.collect::<Vec<_>>().join("\n"));
+        }
+
+        for input in inputs {
+            let scan = parse(&input).unwrap();
+            let abyss = scan.sand_into_abyss(SandMethod::Replay);
+            assert_eq!(scan.sand_into_abyss(SandMethod::Trail), abyss, "{input}");
+            let floor = scan.sand_on_floor(SandMethod::Replay);
+            assert_eq!(scan.sand_on_floor(SandMethod::Trail), floor, "{input}");
+            assert_eq!(scan.sand_on_floor_by_rows(), floor, "{input}");
+        }
+    }
+
+    #[test]
+    fn day_14_render() {
+        let input = parse(INPUT).unwrap();
+        assert_eq!(
+            input.to_string(),
+            "......+...
+..........
+..........
+..........
+....#...##
+....#...#.
+..###...#.
+........#.
+........#.
+#########."
+        );
+
+        let dir = std::env::temp_dir().join(format!("aoc22_day14_{}", std::process::id()));
+        let resting = input
+            .animate(false, 5, &FrameOutput::Text(dir.clone()))
+            .unwrap();
+        assert_eq!(resting, 24);
+        // Empty cave, then after 5, 10, 15, 20 and 24 grains
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 6);
+        assert_eq!(
+            fs::read_to_string(dir.join("frame_00005.txt")).unwrap(),
+            "......+...
+..........
+......o...
+.....ooo..
+....#ooo##
+...o#ooo#.
+..###ooo#.
+....oooo#.
+.o.ooooo#.
+#########."
+        );
+
+        input
+            .animate(true, 100, &FrameOutput::Ppm(dir.clone()))
+            .unwrap();
+        let ppm = fs::read(dir.join("frame_00001.ppm")).unwrap();
+        // The floor is 2 blocks below the lowest rock, and the pile grows a block per row
+        let header = b"P6\n21 12\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+        assert_eq!(ppm.len(), header.len() + 21 * 12 * 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn day_14_bounds() {
+        // Further than the old fixed 700x200 map, and wide enough for the whole pile
+        let input = parse("800,200 -> 800,210 -> 790,210\n10,3 -> 12,3").unwrap();
+        assert_eq!(part1(&input), 0);
+        assert_eq!(part2(&input), 212 * 212);
+
+        let err = parse("498,4 -> 498,6\n503,4 -> 9999,4").unwrap_err();
+        assert_eq!(err, ParseError::new(14, 2, 10, "coordinates up to 5000"));
+        let err = parse("498,4 -> 496,6").unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::new(14, 1, 10, "a point in line with the previous one")
+        );
+    }
 }
@@ -3,6 +3,9 @@ use std::ops::RangeInclusive;
 use aoc_parse::prelude::*;
 use itertools::Itertools;
 
+use crate::error::ParseError;
+use crate::solver::{found, DaySolver, Solver};
+
 #[derive(Debug)]
 struct Point {
     pub x: isize,
@@ -15,23 +18,45 @@ struct BeaconInfo {
     pub beacon: Point,
 }
 
-#[aoc_generator(day15)]
-fn parse(input: &str) -> Vec<BeaconInfo> {
-    let info = parser!(
-        "Sensor at x=" sensor_x:isize ", y=" sensor_y:isize ": closest beacon is at x=" beacon_x:isize ", y=" beacon_y:isize
-        => BeaconInfo { sensor: Point { x: sensor_x, y: sensor_y }, beacon: Point { x: beacon_x, y: beacon_y } }
-    );
-    parser!(lines(info)).parse(input).unwrap()
+impl Point {
+    fn distance(&self, x: isize, y: isize) -> isize {
+        (self.x - x).abs() + (self.y - y).abs()
+    }
 }
 
-#[aoc(day15, part1)]
-fn part1(input: &[BeaconInfo]) -> isize {
-    impossible_positions_for_axis(input, Axis::Row(2000000), None)
+impl BeaconInfo {
+    /// Everything at this distance or closer to the sensor can't hold another beacon
+    fn radius(&self) -> isize {
+        self.sensor.distance(self.beacon.x, self.beacon.y)
+    }
 }
 
-#[aoc(day15, part2)]
-fn part2(input: &[BeaconInfo]) -> isize {
-    find_beacon_frequency(input, 4000000)
+/// Every sensor, with the closest beacon it detected
+#[derive(Debug)]
+pub struct SensorField {
+    infos: Vec<BeaconInfo>,
+}
+
+pub enum Axis {
+    Row(isize),
+    Col(isize),
+}
+
+impl Axis {
+    // Position of the point along the axis, and its distance to the axis
+    fn project(&self, point: &Point) -> (isize, isize) {
+        match self {
+            Axis::Row(row) => (point.x, (point.y - row).abs()),
+            Axis::Col(col) => (point.y, (point.x - col).abs()),
+        }
+    }
+}
+
+const PART1_ROW: isize = 2000000;
+const PART2_LIMIT: isize = 4000000;
+
+pub fn tuning_frequency((x, y): (isize, isize)) -> isize {
+    x * 4000000 + y
 }
 
 fn coalesce_ranges(
@@ -42,82 +67,166 @@ fn coalesce_ranges(
     let min_end = *a.end().min(b.end());
     let max_start = *a.start().max(b.start());
     let max_end = *a.end().max(b.end());
-    if max_start > min_end {
+    // Ranges that touch are merged too, so that gaps are easy to spot
+    if max_start > min_end + 1 {
         return Err((min_start..=min_end, max_start..=max_end));
     }
     Ok(min_start..=max_end)
 }
 
-enum Axis {
-    Row(isize),
-    Col(isize),
-}
+impl SensorField {
+    pub fn parse(input: &str) -> Result<SensorField, ParseError> {
+        let info = parser!(
+            "Sensor at x=" sensor_x:isize ", y=" sensor_y:isize ": closest beacon is at x=" beacon_x:isize ", y=" beacon_y:isize
+            => BeaconInfo { sensor: Point { x: sensor_x, y: sensor_y }, beacon: Point { x: beacon_x, y: beacon_y } }
+        );
+        let infos = parser!(lines(info))
+            .parse(input)
+            .map_err(|err| ParseError::from_aoc_parse(15, err))?;
+        Ok(SensorField { infos })
+    }
 
-fn impossible_positions_for_axis(input: &[BeaconInfo], axis: Axis, limit: Option<isize>) -> isize {
-    input
-        .iter()
-        .filter_map(|info| {
-            let dx = (info.sensor.x - info.beacon.x).abs();
-            let dy = (info.sensor.y - info.beacon.y).abs();
-            let dist = dx + dy;
-            match axis {
-                Axis::Row(row) => {
-                    let dist_to_row = (info.sensor.y - row).abs();
-                    let a = dist - dist_to_row;
-                    if a > 0 {
-                        let min = match limit {
-                            None => info.sensor.x - a,
-                            Some(_) => (info.sensor.x - a).max(0),
-                        };
-                        let max = match limit {
-                            None => info.sensor.x + a,
-                            Some(l) => (info.sensor.x + a).min(l),
-                        };
-                        return Some(min..=max);
-                    }
-                    return None;
-                }
-                Axis::Col(col) => {
-                    let dist_to_col = (info.sensor.x - col).abs();
-                    let a = dist - dist_to_col;
-                    if a > 0 {
-                        let min = match limit {
-                            None => info.sensor.y - a,
-                            Some(_) => (info.sensor.y - a).max(0),
-                        };
-                        let max = match limit {
-                            None => info.sensor.y + a,
-                            Some(l) => (info.sensor.y + a).min(l),
-                        };
-                        return Some(min..=max);
-                    }
+    // Sorted, merged ranges of the axis that some sensor can see.
+    // With a limit, only what's between 0 and the limit is kept.
+    fn covered_ranges(&self, axis: &Axis, limit: Option<isize>) -> Vec<RangeInclusive<isize>> {
+        self.infos
+            .iter()
+            .filter_map(|info| {
+                let (center, dist_to_axis) = axis.project(&info.sensor);
+                let a = info.radius() - dist_to_axis;
+                if a < 0 {
                     return None;
                 }
+                let (min, max) = match limit {
+                    None => (center - a, center + a),
+                    Some(l) => ((center - a).max(0), (center + a).min(l)),
+                };
+                (min <= max).then_some(min..=max)
+            })
+            .sorted_by_key(|range| *range.start())
+            .coalesce(coalesce_ranges)
+            .collect()
+    }
+
+    /// Number of positions on the axis where a beacon can't be.
+    /// Known beacons on the axis don't count : there is a beacon there.
+    pub fn covered_count(&self, axis: Axis, limit: Option<isize>) -> isize {
+        let ranges = self.covered_ranges(&axis, limit);
+        let covered: isize = ranges
+            .iter()
+            .map(|range| range.end() - range.start() + 1)
+            .sum();
+        let beacons = self
+            .infos
+            .iter()
+            .filter_map(|info| match axis.project(&info.beacon) {
+                (position, 0) => Some(position),
+                _ => None,
+            })
+            .filter(|position| ranges.iter().any(|range| range.contains(position)))
+            .unique()
+            .count();
+        covered - beacons as isize
+    }
+
+    // First position between 0 and limit that no sensor can see on the axis
+    fn first_uncovered(&self, axis: Axis, limit: isize) -> Option<isize> {
+        let mut next = 0;
+        for range in self.covered_ranges(&axis, Some(limit)) {
+            if *range.start() > next {
+                return Some(next);
             }
-        })
-        .sorted_by(|a, b| a.start().cmp(b.start()))
-        .coalesce(coalesce_ranges)
-        .map(|range| range.end() - range.start() + 1)
-        .sum::<isize>()
-        - 1
-}
-
-fn find_beacon_frequency(input: &[BeaconInfo], limit: isize) -> isize {
-    // TODO: optimize
-    let y = (0isize..)
-        .find(|y| impossible_positions_for_axis(input, Axis::Row(*y), Some(limit)) < limit)
-        .unwrap();
-    let x = (0isize..)
-        .find(|x| impossible_positions_for_axis(input, Axis::Col(*x), Some(limit)) < limit)
-        .unwrap();
-    x * 4000000 + y
+            next = next.max(range.end() + 1);
+        }
+        (next <= limit).then_some(next)
+    }
+
+    /// Reference implementation, scanning rows until one has a hole
+    pub fn find_distress_beacon_row_scan(&self, limit: isize) -> Option<(isize, isize)> {
+        (0..=limit).find_map(|y| self.first_uncovered(Axis::Row(y), limit).map(|x| (x, y)))
+    }
+
+    /// Position between 0 and limit on both axis that no sensor can see
+    pub fn find_distress_beacon(&self, limit: isize) -> Option<(isize, isize)> {
+        // Since there is only one possible position, it has to be right outside the range of
+        // several sensors. That's on the diagonals lines just outside their diamond shapes :
+        // x + y = a (going down-left) and x - y = b (going down-right).
+        // So instead of scanning rows, we only check where those lines cross each other
+        // (or cross the border of the search area).
+        let mut down_left = Vec::with_capacity(self.infos.len() * 2);
+        let mut down_right = Vec::with_capacity(self.infos.len() * 2);
+        for info in &self.infos {
+            let r = info.radius() + 1;
+            let (x, y) = (info.sensor.x, info.sensor.y);
+            down_left.extend([x + y - r, x + y + r]);
+            down_right.extend([x - y - r, x - y + r]);
+        }
+
+        let crossings = down_left
+            .iter()
+            .cartesian_product(down_right.iter())
+            .filter(|(a, b)| (*a + *b) % 2 == 0)
+            .map(|(a, b)| ((a + b) / 2, (a - b) / 2));
+        // The position can also be against the border of the area, with a sensor on one side only
+        let borders = down_left
+            .iter()
+            .flat_map(|a| [(0, *a), (limit, a - limit), (*a, 0), (a - limit, limit)])
+            .chain(
+                down_right
+                    .iter()
+                    .flat_map(|b| [(0, -b), (limit, limit - b), (*b, 0), (b + limit, limit)]),
+            );
+
+        crossings
+            .chain(borders)
+            .filter(|(x, y)| (0..=limit).contains(x) && (0..=limit).contains(y))
+            .find(|(x, y)| {
+                self.infos
+                    .iter()
+                    .all(|info| info.sensor.distance(*x, *y) > info.radius())
+            })
+    }
+}
+
+#[aoc_generator(day15)]
+fn parse(input: &str) -> Result<SensorField, ParseError> {
+    SensorField::parse(input)
+}
+
+#[aoc(day15, part1)]
+fn part1(input: &SensorField) -> isize {
+    input.covered_count(Axis::Row(PART1_ROW), None)
+}
+
+// None if no position is left for the distress beacon
+#[aoc(day15, part2)]
+fn part2(input: &SensorField) -> Option<isize> {
+    let position = input.find_distress_beacon(PART2_LIMIT)?;
+    Some(tuning_frequency(position))
+}
+
+// Reference implementation, much slower
+#[aoc(day15, part2, row_scan)]
+fn part2_row_scan(input: &SensorField) -> Option<isize> {
+    let position = input.find_distress_beacon_row_scan(PART2_LIMIT)?;
+    Some(tuning_frequency(position))
+}
+
+pub(crate) fn generator(input: &str) -> Result<Box<dyn Solver>, ParseError> {
+    let solver = DaySolver::new(
+        15,
+        parse(input)?,
+        |i| Ok(part1(i).into()),
+        |i| found(15, 2, part2(i)),
+    );
+    Ok(Box::new(solver))
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
 
-    const INPUT: &'static str = "Sensor at x=2, y=18: closest beacon is at x=-2, y=15
+    const INPUT: &str = "Sensor at x=2, y=18: closest beacon is at x=-2, y=15
 Sensor at x=9, y=16: closest beacon is at x=10, y=16
 Sensor at x=13, y=2: closest beacon is at x=15, y=3
 Sensor at x=12, y=14: closest beacon is at x=10, y=16
@@ -134,11 +243,42 @@ Sensor at x=20, y=1: closest beacon is at x=15, y=3";
 
     #[test]
     fn solve_day_15() {
-        let input = parse(INPUT);
-        //assert_eq!(
-        //impossible_positions_for_axis(&input, Axis::Row(10), None),
-        //26
-        //);
-        assert_eq!(find_beacon_frequency(&input, 20), 56000011);
+        let input = parse(INPUT).unwrap();
+        assert_eq!(input.covered_count(Axis::Row(10), None), 26);
+        assert_eq!(input.find_distress_beacon_row_scan(20), Some((14, 11)));
+        assert_eq!(input.find_distress_beacon(20), Some((14, 11)));
+        assert_eq!(tuning_frequency((14, 11)), 56000011);
+    }
+
+    #[test]
+    fn day_15_fast_matches_row_scan() {
+        // Sensors all around a chosen position, each one reaching right before it :
+        // that position is the only one left, wherever it is in the area
+        for position in [(7, 13), (0, 0), (20, 5), (11, 20), (20, 20), (1, 19)] {
+            let (px, py) = position;
+            let infos = (0..=20)
+                .step_by(4)
+                .cartesian_product((0..=20).step_by(4))
+                .map(|(x, y)| Point { x, y })
+                .filter(|sensor| sensor.distance(px, py) >= 2)
+                .map(|sensor| BeaconInfo {
+                    beacon: Point {
+                        x: sensor.x + sensor.distance(px, py) - 1,
+                        y: sensor.y,
+                    },
+                    sensor,
+                })
+                .collect();
+            let field = SensorField { infos };
+            assert_eq!(field.find_distress_beacon(20), Some(position));
+            assert_eq!(field.find_distress_beacon_row_scan(20), Some(position));
+        }
+    }
+
+    #[test]
+    fn day_15_parse_error() {
+        let err = parse("Sensor at x=2, y=18: closest beacon is at x=-2, y=15\nSensor at x=9")
+            .unwrap_err();
+        assert_eq!(err, ParseError::new(15, 2, 14, "expected \", y=\""));
     }
 }
@@ -1,3 +1,6 @@
+use crate::error::{Line, ParseError};
+use crate::solver::{DaySolver, Solver};
+
 #[derive(PartialEq, Eq)]
 enum Choice {
     Rock,
@@ -6,15 +9,15 @@ enum Choice {
 }
 
 impl Choice {
-    fn from_str(input: &str) -> Choice {
+    fn from_str(input: &str) -> Option<Choice> {
         match input {
-            "A" => Choice::Rock,
-            "B" => Choice::Paper,
-            "C" => Choice::Scissors,
-            "X" => Choice::Rock,
-            "Y" => Choice::Paper,
-            "Z" => Choice::Scissors,
-            _ => unimplemented!(),
+            "A" => Some(Choice::Rock),
+            "B" => Some(Choice::Paper),
+            "C" => Some(Choice::Scissors),
+            "X" => Some(Choice::Rock),
+            "Y" => Some(Choice::Paper),
+            "Z" => Some(Choice::Scissors),
+            _ => None,
         }
     }
 
@@ -55,12 +58,12 @@ enum Outcome {
 }
 
 impl Outcome {
-    fn from_str(input: &str) -> Self {
+    fn from_str(input: &str) -> Option<Self> {
         match input {
-            "X" => Outcome::Lose,
-            "Y" => Outcome::Draw,
-            "Z" => Outcome::Win,
-            _ => unimplemented!(),
+            "X" => Some(Outcome::Lose),
+            "Y" => Some(Outcome::Draw),
+            "Z" => Some(Outcome::Win),
+            _ => None,
         }
     }
 
@@ -88,19 +91,29 @@ impl Round {
 }
 
 #[aoc_generator(day2)]
-fn parse(input: &str) -> Vec<Round> {
-    input
-        .lines()
+fn parse(input: &str) -> Result<Vec<Round>, ParseError> {
+    Line::all(2, input)
         .map(|line| {
-            let mut splitted = line.split(' ');
-            let opponent = Choice::from_str(splitted.next().unwrap());
-            let second = splitted.next().unwrap();
+            let mut splitted = line.text.split(' ');
+            let first = line.field(splitted.next(), "A, B or C")?;
+            let opponent = match first {
+                "A" | "B" | "C" => Choice::from_str(first),
+                _ => None,
+            }
+            .ok_or_else(|| line.error(first, "A, B or C"))?;
+            let second = line.field(splitted.next(), "X, Y or Z")?;
             let mine = Choice::from_str(second);
             let wanted_outcome = Outcome::from_str(second);
-            Round {
-                opponent,
-                mine,
-                wanted_outcome,
+            if let Some(extra) = splitted.next() {
+                return Err(line.error(extra, "the end of the line"));
+            }
+            match (mine, wanted_outcome) {
+                (Some(mine), Some(wanted_outcome)) => Ok(Round {
+                    opponent,
+                    mine,
+                    wanted_outcome,
+                }),
+                _ => Err(line.error(second, "X, Y or Z")),
             }
         })
         .collect()
@@ -125,18 +138,48 @@ fn part2(input: &[Round]) -> u32 {
         .sum()
 }
 
+pub(crate) fn generator(input: &str) -> Result<Box<dyn Solver>, ParseError> {
+    let solver = DaySolver::new(
+        2,
+        parse(input)?,
+        |i| Ok(part1(i).into()),
+        |i| Ok(part2(i).into()),
+    );
+    Ok(Box::new(solver))
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
 
-    const INPUT: &'static str = "A Y
+    const INPUT: &str = "A Y
 B X
 C Z";
 
     #[test]
     fn solve_day_2() {
-        let input = parse(INPUT);
+        let input = parse(INPUT).unwrap();
         assert_eq!(part1(&input), 15);
         assert_eq!(part2(&input), 12);
     }
+
+    #[test]
+    fn day_2_parse_error() {
+        assert_eq!(
+            parse("A Y\nD X").err(),
+            Some(ParseError::new(2, 2, 1, "A, B or C"))
+        );
+        assert_eq!(
+            parse("A W").err(),
+            Some(ParseError::new(2, 1, 3, "X, Y or Z"))
+        );
+        assert_eq!(
+            parse("A").err(),
+            Some(ParseError::new(2, 1, 2, "X, Y or Z"))
+        );
+        assert_eq!(
+            parse("A Y Z").err(),
+            Some(ParseError::new(2, 1, 5, "the end of the line"))
+        );
+    }
 }
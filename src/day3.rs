@@ -1,20 +1,29 @@
 use std::collections::HashSet;
 
+use crate::error::{Line, ParseError};
+use crate::solver::{found, DaySolver, Solver};
+
 struct Rucksack {
     one: String,
     two: String,
 }
 
 #[aoc_generator(day3)]
-fn parse(input: &str) -> Vec<Rucksack> {
-    input
-        .lines()
-        .map(|l| {
+fn parse(input: &str) -> Result<Vec<Rucksack>, ParseError> {
+    Line::all(3, input)
+        .map(|line| {
+            let l = line.text;
+            if let Some(idx) = l.find(|c: char| !c.is_ascii_alphabetic()) {
+                return Err(line.error(&l[idx..], "an item letter"));
+            }
+            if !l.len().is_multiple_of(2) {
+                return Err(line.error_at_end("an even number of items"));
+            }
             let compartment_size = l.len() / 2;
-            Rucksack {
+            Ok(Rucksack {
                 one: l[0..compartment_size].to_string(),
                 two: l[compartment_size..].to_string(),
-            }
+            })
         })
         .collect()
 }
@@ -27,23 +36,28 @@ fn item_priority(c: char) -> u32 {
     }
 }
 
+// None if a rucksack doesn't share any item with its other compartment
 #[aoc(day3, part1)]
-fn part1(input: &[Rucksack]) -> u32 {
+fn part1(input: &[Rucksack]) -> Option<u32> {
     input
         .iter()
         .map(|sack| {
             let one = sack.one.chars().collect::<HashSet<char>>();
             let two = sack.two.chars().collect::<HashSet<char>>();
-            item_priority((&one & &two).into_iter().next().unwrap())
+            (&one & &two).into_iter().next().map(item_priority)
         })
         .sum()
 }
 
+// None if a group isn't made of three elves sharing a badge
 #[aoc(day3, part2)]
-fn part2(input: &[Rucksack]) -> u32 {
+fn part2(input: &[Rucksack]) -> Option<u32> {
     input
         .chunks(3)
         .map(|sacks| {
+            if sacks.len() != 3 {
+                return None;
+            }
             let one = sacks[0]
                 .one
                 .chars()
@@ -60,16 +74,26 @@ fn part2(input: &[Rucksack]) -> u32 {
                 .chain(sacks[2].two.chars())
                 .collect::<HashSet<char>>();
             let inter = &(&one & &two) & &three;
-            item_priority(inter.into_iter().next().unwrap())
+            inter.into_iter().next().map(item_priority)
         })
         .sum()
 }
 
+pub(crate) fn generator(input: &str) -> Result<Box<dyn Solver>, ParseError> {
+    let solver = DaySolver::new(
+        3,
+        parse(input)?,
+        |i| found(3, 1, part1(i)),
+        |i| found(3, 2, part2(i)),
+    );
+    Ok(Box::new(solver))
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
 
-    const INPUT: &'static str = "vJrwpWtwJgWrhcsFMMfFFhFp
+    const INPUT: &str = "vJrwpWtwJgWrhcsFMMfFFhFp
 jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL
 PmmdzqPrVvPwwTWBwg
 wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn
@@ -78,8 +102,24 @@ CrZsJsPPZsGzwwsLwLmpwMDw";
 
     #[test]
     fn solve_day_3() {
-        let input = parse(INPUT);
-        assert_eq!(part1(&input), 157);
-        assert_eq!(part2(&input), 70);
+        let input = parse(INPUT).unwrap();
+        assert_eq!(part1(&input), Some(157));
+        assert_eq!(part2(&input), Some(70));
+
+        let input = parse("abAB\nabab").unwrap();
+        assert_eq!(part1(&input), None);
+        assert_eq!(part2(&input), None);
+    }
+
+    #[test]
+    fn day_3_parse_error() {
+        assert_eq!(
+            parse("vJrwpWtwJgWrhcsFMMfFFhFp\nab1d").err(),
+            Some(ParseError::new(3, 2, 3, "an item letter"))
+        );
+        assert_eq!(
+            parse("abc").err(),
+            Some(ParseError::new(3, 1, 4, "an even number of items"))
+        );
     }
 }
@@ -1,6 +1,9 @@
 use aoc_parse::prelude::*;
 use std::ops::RangeInclusive;
 
+use crate::error::ParseError;
+use crate::solver::{DaySolver, Solver};
+
 #[derive(Debug)]
 struct Assignment {
     one: RangeInclusive<usize>,
@@ -16,16 +19,15 @@ fn range_overlaps(r: &RangeInclusive<usize>, r2: &RangeInclusive<usize>) -> bool
 }
 
 #[aoc_generator(day4)]
-fn parse(input: &str) -> anyhow::Result<Vec<Assignment>> {
-    aoc_parse(
-        input,
-        parser!(
-            lines(
-                a:usize "-" b:usize "," c:usize "-" d:usize
-                => Assignment { one: (a..=b), two: (c..=d) }
-            )
-        ),
+fn parse(input: &str) -> Result<Vec<Assignment>, ParseError> {
+    parser!(
+        lines(
+            a:usize "-" b:usize "," c:usize "-" d:usize
+            => Assignment { one: (a..=b), two: (c..=d) }
+        )
     )
+    .parse(input)
+    .map_err(|err| ParseError::from_aoc_parse(4, err))
 }
 
 #[aoc(day4, part1)]
@@ -44,11 +46,21 @@ fn part2(input: &[Assignment]) -> usize {
         .count()
 }
 
+pub(crate) fn generator(input: &str) -> Result<Box<dyn Solver>, ParseError> {
+    let solver = DaySolver::new(
+        4,
+        parse(input)?,
+        |i| Ok(part1(i).into()),
+        |i| Ok(part2(i).into()),
+    );
+    Ok(Box::new(solver))
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
 
-    const INPUT: &'static str = "2-4,6-8
+    const INPUT: &str = "2-4,6-8
 2-3,4-5
 5-7,7-9
 2-8,3-7
@@ -61,4 +73,10 @@ pub mod tests {
         assert_eq!(part1(&input), 2);
         assert_eq!(part2(&input), 4);
     }
+
+    #[test]
+    fn day_4_parse_error() {
+        let err = parse("2-4,6-8\n2-x,4-5").unwrap_err();
+        assert_eq!((err.day, err.line, err.column), (4, 2, 3));
+    }
 }
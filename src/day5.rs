@@ -1,3 +1,6 @@
+use crate::error::{Line, ParseError};
+use crate::solver::{found, DaySolver, Solver};
+
 #[derive(Debug)]
 struct Rearrangement {
     stacks: Stacks,
@@ -15,94 +18,157 @@ struct Instruction {
 struct Stacks(Vec<Vec<String>>);
 
 impl Stacks {
-    pub fn handle_instruction(&mut self, inst: &Instruction) {
+    pub fn handle_instruction(&mut self, inst: &Instruction) -> Option<()> {
         let len = self.0[inst.from - 1].len();
         let drained = self.0[inst.from - 1]
-            .drain(len - inst.size..)
+            .drain(len.checked_sub(inst.size)?..)
             .rev()
             .collect::<Vec<_>>();
-        self.0[inst.to - 1].extend(drained)
+        self.0[inst.to - 1].extend(drained);
+        Some(())
     }
 
-    pub fn handle_instruction_two(&mut self, inst: &Instruction) {
+    pub fn handle_instruction_two(&mut self, inst: &Instruction) -> Option<()> {
         let len = self.0[inst.from - 1].len();
         let drained = self.0[inst.from - 1]
-            .drain(len - inst.size..)
+            .drain(len.checked_sub(inst.size)?..)
             .collect::<Vec<_>>();
-        self.0[inst.to - 1].extend(drained)
+        self.0[inst.to - 1].extend(drained);
+        Some(())
     }
 
     pub fn scan_top(&self) -> String {
+        // Empty stacks have no crate on top, so they don't show up in the message
         self.0
             .iter()
-            .map(|stack| {
-                stack
-                    .iter()
-                    .rev()
-                    .find(|x| x.as_str() != " ")
-                    .unwrap()
-                    .to_string()
-            })
+            .filter_map(|stack| stack.iter().rev().find(|x| x.as_str() != " "))
+            .map(|x| x.to_string())
             .collect()
     }
 }
 
 impl Instruction {
-    pub fn parse(input: &str) -> Self {
-        let input: Vec<&str> = input.split(' ').collect();
-        let size = input[1].parse().unwrap();
-        let from = input[3].parse().unwrap();
-        let to = input[5].parse().unwrap();
-        Instruction { size, from, to }
+    pub fn parse(line: Line, stack_count: usize) -> Result<Self, ParseError> {
+        let mut words = line.text.split(' ');
+        let mut next_number = |keyword: &str| -> Result<usize, ParseError> {
+            let word = line.field(words.next(), &format!("{keyword:?}"))?;
+            if word != keyword {
+                return Err(line.error(word, format!("{keyword:?}")));
+            }
+            let value = line.field(words.next(), "a number")?;
+            line.parse(value, "a number")
+        };
+        let size = next_number("move")?;
+        let from = next_number("from")?;
+        let to = next_number("to")?;
+        if let Some(stack) = [from, to]
+            .into_iter()
+            .find(|stack| !(1..=stack_count).contains(stack))
+        {
+            return Err(
+                line.error_at_end(format!("a stack between 1 and {stack_count}, got {stack}"))
+            );
+        }
+        Ok(Instruction { size, from, to })
+    }
+}
+
+// The drawing is made of cells 3 characters wide, with a space between them. Every cell has to
+// match, and since we stop at the first one that doesn't, everything before it is ASCII.
+fn check_cells(
+    line: Line,
+    expected: &str,
+    matches: impl Fn(usize, &[u8]) -> bool,
+) -> Result<(), ParseError> {
+    let wrong = line
+        .text
+        .as_bytes()
+        .chunks(4)
+        .enumerate()
+        .position(|(i, cell)| {
+            let (cell, separator) = cell.split_at(cell.len().min(3));
+            !matches(i, cell) || separator.iter().any(|&c| c != b' ')
+        });
+    match wrong {
+        Some(i) => Err(line.error(&line.text[i * 4..], expected)),
+        None => Ok(()),
     }
 }
 
 #[aoc_generator(day5)]
-fn parse(input: &str) -> Rearrangement {
-    let mut input = input.split("\n\n");
-    let plan = input.next().unwrap();
-    let plan: Vec<&str> = plan.lines().collect();
-    let length = plan[plan.len() - 1].len() / 4 + 1;
+fn parse(input: &str) -> Result<Rearrangement, ParseError> {
+    let mut lines = Line::all(5, input);
+    let plan: Vec<Line> = lines.by_ref().take_while(|l| !l.text.is_empty()).collect();
+    let Some((numbers, drawing)) = plan.split_last() else {
+        return Err(ParseError::new(5, 1, 1, "a drawing of the stacks"));
+    };
+    // The last line of the drawing numbers the stacks, in order
+    check_cells(*numbers, "the stack numbers", |i, cell| {
+        let number = format!(" {}", i + 1);
+        cell.starts_with(number.as_bytes()) && cell[number.len()..].iter().all(|&c| c == b' ')
+    })?;
+    let length = numbers.text.len().div_ceil(4);
+    for line in drawing {
+        check_cells(*line, "a crate like [A] or an empty slot", |i, cell| {
+            i < length
+                && match cell {
+                    [b'[', name, b']'] => name.is_ascii_uppercase(),
+                    _ => cell.iter().all(|&c| c == b' '),
+                }
+        })?;
+    }
     let mut stacks: Vec<Vec<String>> = Vec::with_capacity(length);
     for _ in 0..length {
-        stacks.push(Vec::with_capacity(plan.len() - 1));
+        stacks.push(Vec::with_capacity(drawing.len()));
     }
-    for l in plan.iter().rev().skip(1) {
-        for stack in 0..length {
-            let idx = stack * 4 + 1;
-            if let Some(v) = l.get(idx..=idx) {
+    for l in drawing.iter().rev() {
+        for (i, stack) in stacks.iter_mut().enumerate() {
+            let idx = i * 4 + 1;
+            if let Some(v) = l.text.get(idx..=idx) {
                 if v != " " {
-                    stacks[stack].push(v.to_string());
+                    stack.push(v.to_string());
                 }
             }
         }
     }
 
-    let instructions = input.next().unwrap();
-    let instructions = instructions.lines().map(Instruction::parse).collect();
+    let instructions = lines
+        .map(|line| Instruction::parse(line, length))
+        .collect::<Result<_, _>>()?;
 
-    Rearrangement {
+    Ok(Rearrangement {
         stacks: Stacks(stacks),
         instructions,
-    }
+    })
 }
 
+// None if an instruction moves more crates than its stack holds
 #[aoc(day5, part1)]
-fn part1(input: &Rearrangement) -> String {
+fn part1(input: &Rearrangement) -> Option<String> {
     let mut stacks = input.stacks.clone();
     for inst in &input.instructions {
-        stacks.handle_instruction(inst);
+        stacks.handle_instruction(inst)?;
     }
-    stacks.scan_top()
+    Some(stacks.scan_top())
 }
 
 #[aoc(day5, part2)]
-fn part2(input: &Rearrangement) -> String {
+fn part2(input: &Rearrangement) -> Option<String> {
     let mut stacks = input.stacks.clone();
     for inst in &input.instructions {
-        stacks.handle_instruction_two(inst);
+        stacks.handle_instruction_two(inst)?;
     }
-    stacks.scan_top()
+    Some(stacks.scan_top())
+}
+
+pub(crate) fn generator(input: &str) -> Result<Box<dyn Solver>, ParseError> {
+    let solver = DaySolver::new(
+        5,
+        parse(input)?,
+        |i| found(5, 1, part1(i)),
+        |i| found(5, 2, part2(i)),
+    );
+    Ok(Box::new(solver))
 }
 
 #[cfg(test)]
@@ -111,18 +177,53 @@ pub mod tests {
 
     // I have a vim extension that trims my end of line on save,
     // which messes up the input. Let's import is instead
-    const INPUT: &'static str = include_str!("day5_test.txt");
+    const INPUT: &str = include_str!("day5_test.txt");
 
     #[test]
     fn solve_day_5() {
-        let input = parse(INPUT);
-        assert_eq!(part1(&input), "CMZ");
-        assert_eq!(part2(&input), "MCD");
+        let input = parse(INPUT).unwrap();
+        assert_eq!(part1(&input).as_deref(), Some("CMZ"));
+        assert_eq!(part2(&input).as_deref(), Some("MCD"));
+    }
+
+    #[test]
+    fn day_5_oversized_move() {
+        let input = parse(&INPUT.replace("move 3 from 1 to 3", "move 4 from 1 to 3")).unwrap();
+        assert_eq!(part1(&input), None);
+        assert_eq!(part2(&input), None);
+    }
+
+    #[test]
+    fn day_5_parse_error() {
+        let err = parse("move 1 from 1 to 2").unwrap_err();
+        assert_eq!(err, ParseError::new(5, 1, 1, "the stack numbers"));
+        let err = parse(&INPUT.replace(" 2 ", " 4 ")).unwrap_err();
+        assert_eq!(err, ParseError::new(5, 4, 5, "the stack numbers"));
+        let err = parse("garbage\n 1 \n\nmove 1 from 1 to 1").unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::new(5, 1, 1, "a crate like [A] or an empty slot")
+        );
+        let err = parse(&INPUT.replace("[M]", "[M)")).unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::new(5, 3, 5, "a crate like [A] or an empty slot")
+        );
+        let err = parse("[A] [B]\n 1 \n\nmove 1 from 1 to 1").unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::new(5, 1, 5, "a crate like [A] or an empty slot")
+        );
+        let err = parse(&INPUT.replace("move 2 from 2 to 1", "move 2 from 2 to 4")).unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::new(5, 8, 19, "a stack between 1 and 3, got 4")
+        );
     }
 
     #[test]
     fn day_5_scan_top() {
-        let input = parse(INPUT);
+        let input = parse(INPUT).unwrap();
         assert_eq!(input.stacks.scan_top(), "NDP".to_string());
     }
 }
@@ -1,26 +1,36 @@
 use std::collections::HashSet;
 
+use crate::error::ParseError;
+use crate::solver::{found, DaySolver, Solver};
+
 #[aoc(day6, part1)]
-fn part1(input: &str) -> usize {
+fn part1(input: &str) -> Option<usize> {
     solve(input, 4)
 }
 
 #[aoc(day6, part2)]
-fn part2(input: &str) -> usize {
+fn part2(input: &str) -> Option<usize> {
     solve(input, 14)
 }
 
-fn solve(input: &str, window_size: usize) -> usize {
+// None if no window of distinct characters shows up in the datastream
+fn solve(input: &str, window_size: usize) -> Option<usize> {
     let input: Vec<char> = input.chars().collect();
-    let (idx, _) = input
-        .windows(window_size)
-        .enumerate()
-        .find(|(_idx, w)| {
-            let set = w.iter().collect::<HashSet<_>>();
-            set.len() == window_size
-        })
-        .unwrap();
-    idx + window_size
+    let (idx, _) = input.windows(window_size).enumerate().find(|(_idx, w)| {
+        let set = w.iter().collect::<HashSet<_>>();
+        set.len() == window_size
+    })?;
+    Some(idx + window_size)
+}
+
+pub(crate) fn generator(input: &str) -> Result<Box<dyn Solver>, ParseError> {
+    let solver = DaySolver::new(
+        6,
+        input.to_string(),
+        |i| found(6, 1, part1(i)),
+        |i| found(6, 2, part2(i)),
+    );
+    Ok(Box::new(solver))
 }
 
 #[cfg(test)]
@@ -30,16 +40,18 @@ pub mod tests {
     #[test]
     fn solve_day_6() {
         // part 1
-        assert_eq!(part1("bvwbjplbgvbhsrlpgdmjqwftvncz"), 5);
-        assert_eq!(part1("nppdvjthqldpwncqszvftbrmjlhg"), 6);
-        assert_eq!(part1("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg"), 10);
-        assert_eq!(part1("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw"), 11);
+        assert_eq!(part1("bvwbjplbgvbhsrlpgdmjqwftvncz"), Some(5));
+        assert_eq!(part1("nppdvjthqldpwncqszvftbrmjlhg"), Some(6));
+        assert_eq!(part1("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg"), Some(10));
+        assert_eq!(part1("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw"), Some(11));
 
         // part2
-        assert_eq!(part2("mjqjpqmgbljsphdztnvjfqwrcgsmlb"), 19);
-        assert_eq!(part2("bvwbjplbgvbhsrlpgdmjqwftvncz"), 23);
-        assert_eq!(part2("nppdvjthqldpwncqszvftbrmjlhg"), 23);
-        assert_eq!(part2("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg"), 29);
-        assert_eq!(part2("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw"), 26);
+        assert_eq!(part2("mjqjpqmgbljsphdztnvjfqwrcgsmlb"), Some(19));
+        assert_eq!(part2("bvwbjplbgvbhsrlpgdmjqwftvncz"), Some(23));
+        assert_eq!(part2("nppdvjthqldpwncqszvftbrmjlhg"), Some(23));
+        assert_eq!(part2("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg"), Some(29));
+        assert_eq!(part2("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw"), Some(26));
+
+        assert_eq!(part1("abca"), None);
     }
 }
@@ -1,5 +1,8 @@
 use std::collections::HashMap;
 
+use crate::error::{Line, ParseError};
+use crate::solver::{found, DaySolver, Solver};
+
 #[derive(Debug, Default)]
 struct Dir {
     files: HashMap<String, Dir>,
@@ -13,44 +16,51 @@ enum Append {
 }
 
 impl Append {
-    fn parse(input: &str) -> Self {
-        if input.starts_with("dir ") {
-            return Append::AddDir {
-                name: input[4..].to_string(),
-            };
+    fn parse(line: Line) -> Result<Self, ParseError> {
+        let mut splitted = line.text.split(' ');
+        let first = line.field(splitted.next(), "a file size or \"dir\"")?;
+        let append = if first == "dir" {
+            let name = line.field(splitted.next(), "a directory name")?;
+            Append::AddDir {
+                name: name.to_string(),
+            }
+        } else {
+            let size: usize = line.parse(first, "a file size or \"dir\"")?;
+            line.field(splitted.next(), "a file name")?;
+            Append::AddFile { size }
+        };
+        if let Some(extra) = splitted.next() {
+            return Err(line.error(extra, "the end of the line"));
         }
-        let mut splitted = input.split(" ");
-        let size: usize = splitted.next().unwrap().parse().unwrap();
-        Append::AddFile { size }
+        Ok(append)
     }
 }
 
 impl Dir {
-    fn append(&mut self, path: &[String], append: Append) {
-        match append {
-            Append::AddFile { size, .. } => self.total_size += size,
-            _ => (),
-        }
-
+    // Returns None if the path goes through a directory we haven't listed
+    fn append(&mut self, path: &[String], append: Append) -> Option<()> {
         if path.is_empty() {
             match append {
                 Append::AddDir { name } => {
                     self.files.insert(name, Dir::default());
                 }
-                _ => (),
+                Append::AddFile { size } => self.total_size += size,
             }
-        } else {
-            let dir = self.files.get_mut(&path[0]).unwrap();
-            dir.append(&path[1..], append);
+            return Some(());
         }
+
+        let size = match append {
+            Append::AddFile { size } => size,
+            Append::AddDir { .. } => 0,
+        };
+        let dir = self.files.get_mut(&path[0])?;
+        dir.append(&path[1..], append)?;
+        self.total_size += size;
+        Some(())
     }
 
     fn scan_part_one(&self, prev: usize) -> usize {
-        let children: usize = self
-            .files
-            .iter()
-            .map(|(_k, dir)| dir.scan_part_one(0))
-            .sum();
+        let children: usize = self.files.values().map(|dir| dir.scan_part_one(0)).sum();
         if self.total_size <= 100000 {
             prev + children + self.total_size
         } else {
@@ -64,25 +74,32 @@ impl Dir {
         }
 
         self.files
-            .iter()
-            .filter_map(|(_k, dir)| dir.scan_part_two(wanted_space))
+            .values()
+            .filter_map(|dir| dir.scan_part_two(wanted_space))
             .chain(Some(self.total_size))
             .min()
     }
 }
 
 #[aoc_generator(day7)]
-fn parse(input: &str) -> Dir {
+fn parse(input: &str) -> Result<Dir, ParseError> {
     let mut root = Dir::default();
     let mut full_path = vec!["/".to_string()];
-    for line in input.lines() {
-        if line == "$ ls" {
+    for line in Line::all(7, input) {
+        if line.text == "$ ls" {
             continue;
         }
-        if line.starts_with("$ cd") {
-            let add_to_path = line.split(' ').skip(2).next().unwrap();
+        if line.text.starts_with("$ cd") {
+            let mut splitted = line.text.split(' ').skip(2);
+            let add_to_path = line.field(splitted.next(), "a directory name")?;
+            if let Some(extra) = splitted.next() {
+                return Err(line.error(extra, "the end of the line"));
+            }
             match add_to_path {
                 "/" => (),
+                ".." if full_path.len() == 1 => {
+                    return Err(line.error(add_to_path, "a directory below /"));
+                }
                 ".." => {
                     full_path.pop();
                 }
@@ -90,9 +107,10 @@ fn parse(input: &str) -> Dir {
             }
             continue;
         }
-        root.append(&full_path[1..], Append::parse(line));
+        root.append(&full_path[1..], Append::parse(line)?)
+            .ok_or_else(|| line.error(line.text, "a listing in a known directory"))?;
     }
-    root
+    Ok(root)
 }
 
 #[aoc(day7, part1)]
@@ -100,18 +118,29 @@ fn part1(input: &Dir) -> usize {
     input.scan_part_one(0)
 }
 
+// None if the files don't fit on the disk, or if there's already enough space for the update
 #[aoc(day7, part2)]
-fn part2(input: &Dir) -> usize {
-    let free_space = 70000000 - input.total_size;
-    let wanted_space = 30000000 - free_space;
-    input.scan_part_two(wanted_space).unwrap()
+fn part2(input: &Dir) -> Option<usize> {
+    let free_space = 70000000usize.checked_sub(input.total_size)?;
+    let wanted_space = 30000000usize.checked_sub(free_space)?;
+    input.scan_part_two(wanted_space)
+}
+
+pub(crate) fn generator(input: &str) -> Result<Box<dyn Solver>, ParseError> {
+    let solver = DaySolver::new(
+        7,
+        parse(input)?,
+        |i| Ok(part1(i).into()),
+        |i| found(7, 2, part2(i)),
+    );
+    Ok(Box::new(solver))
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
 
-    const INPUT: &'static str = "$ cd /
+    const INPUT: &str = "$ cd /
 $ ls
 dir a
 14848514 b.txt
@@ -137,9 +166,31 @@ $ ls
 
     #[test]
     fn solve_day_7() {
-        let input = parse(INPUT);
+        let input = parse(INPUT).unwrap();
         dbg!(&input);
         assert_eq!(part1(&input), 95437);
-        assert_eq!(part2(&input), 24933642);
+        assert_eq!(part2(&input), Some(24933642));
+
+        let input = parse("$ cd /\n$ ls\n1 a").unwrap();
+        assert_eq!(part2(&input), None);
+    }
+
+    #[test]
+    fn day_7_parse_error() {
+        let err = parse("$ cd /\n$ cd ..\n$ ls\n1 a").unwrap_err();
+        assert_eq!(err, ParseError::new(7, 2, 6, "a directory below /"));
+        let err = parse("$ cd / a").unwrap_err();
+        assert_eq!(err, ParseError::new(7, 1, 8, "the end of the line"));
+        let err = parse(&INPUT.replace("584 i", "584 i j")).unwrap_err();
+        assert_eq!(err, ParseError::new(7, 15, 7, "the end of the line"));
+        let err = parse(&INPUT.replace("584 i", "584")).unwrap_err();
+        assert_eq!(err, ParseError::new(7, 15, 4, "a file name"));
+        let err = parse(&INPUT.replace("dir e", "dire")).unwrap_err();
+        assert_eq!(err, ParseError::new(7, 9, 1, "a file size or \"dir\""));
+        let err = parse(&INPUT.replace("$ cd d", "$ cd x")).unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::new(7, 20, 1, "a listing in a known directory")
+        );
     }
 }
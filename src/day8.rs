@@ -1,98 +1,144 @@
-use std::collections::HashMap;
+use crate::error::{Line, ParseError};
+use crate::solver::{DaySolver, Solver};
 
+/// Tree heights, row by row
 #[derive(Debug)]
-pub struct TreeMap {
-    pub map: HashMap<(i32, i32), i32>,
-    pub dimensions: (i32, i32),
+pub struct Forest {
+    heights: Vec<u8>,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// What we know about each tree after looking along every row and column
+struct Survey {
+    visible: Vec<bool>,
+    scenic_scores: Vec<usize>,
+}
+
+impl Forest {
+    pub fn height_at(&self, x: usize, y: usize) -> u8 {
+        self.heights[y * self.width + x]
+    }
+
+    // Walks a line of trees (given as indices in heights), and updates for each tree :
+    // - whether it's visible from where the line starts
+    // - its scenic score, with the viewing distance towards where the line starts
+    fn sweep(
+        &self,
+        line: impl Iterator<Item = usize>,
+        survey: &mut Survey,
+        stack: &mut Vec<(usize, u8)>,
+    ) {
+        // Trees we could still see from further in the line : heights only decrease in there
+        stack.clear();
+        let mut tallest = None;
+        for (distance, idx) in line.enumerate() {
+            let height = self.heights[idx];
+            if tallest.is_none_or(|tallest| height > tallest) {
+                survey.visible[idx] = true;
+                tallest = Some(height);
+            }
+            while stack.last().is_some_and(|(_, h)| *h < height) {
+                stack.pop();
+            }
+            let viewing_distance = match stack.last() {
+                // Blocked by the first tree at least as tall
+                Some((blocking, _)) => distance - blocking,
+                // Or we can see all the way to the edge
+                None => distance,
+            };
+            survey.scenic_scores[idx] *= viewing_distance;
+            stack.push((distance, height));
+        }
+    }
+
+    fn survey(&self) -> Survey {
+        let mut survey = Survey {
+            visible: vec![false; self.heights.len()],
+            scenic_scores: vec![1; self.heights.len()],
+        };
+        let mut stack = Vec::with_capacity(self.width.max(self.height));
+        let (width, height) = (self.width, self.height);
+        for y in 0..height {
+            self.sweep((0..width).map(|x| y * width + x), &mut survey, &mut stack);
+            self.sweep(
+                (0..width).rev().map(|x| y * width + x),
+                &mut survey,
+                &mut stack,
+            );
+        }
+        for x in 0..width {
+            self.sweep((0..height).map(|y| y * width + x), &mut survey, &mut stack);
+            self.sweep(
+                (0..height).rev().map(|y| y * width + x),
+                &mut survey,
+                &mut stack,
+            );
+        }
+        survey
+    }
+
+    pub fn visible_count(&self) -> usize {
+        self.survey().visible.iter().filter(|v| **v).count()
+    }
+
+    pub fn best_scenic_score(&self) -> usize {
+        self.survey().scenic_scores.into_iter().max().unwrap_or(0)
+    }
 }
 
 #[aoc_generator(day8)]
-fn parse(input: &str) -> TreeMap {
-    let mut dim_x = 0;
-    let mut dim_y = 0;
-    let map = input
-        .lines()
-        .enumerate()
-        .flat_map(|(y, l)| {
-            dim_y += 1;
-            dim_x = l.len() as i32;
-            l.chars().enumerate().map(move |(x, c)| {
-                let height = format!("{c}").parse::<i32>().unwrap();
-                ((x as i32, y as i32), height)
-            })
-        })
-        .collect();
-    TreeMap {
-        map,
-        dimensions: (dim_x, dim_y),
+fn parse(input: &str) -> Result<Forest, ParseError> {
+    let mut width = 0;
+    let mut height = 0;
+    let mut heights = Vec::with_capacity(input.len());
+    for line in Line::all(8, input) {
+        if height > 0 && line.text.len() != width {
+            return Err(line.error_at_end(format!("a row of {width} trees")));
+        }
+        for (x, c) in line.text.char_indices() {
+            let tree = c
+                .to_digit(10)
+                .ok_or_else(|| line.error(&line.text[x..], "a tree height digit"))?;
+            heights.push(tree as u8);
+        }
+        height += 1;
+        width = line.text.len();
     }
+    Ok(Forest {
+        heights,
+        width,
+        height,
+    })
 }
 
 #[aoc(day8, part1)]
-fn part1(input: &TreeMap) -> usize {
-    input
-        .map
-        .iter()
-        .filter(|((x, y), height)| {
-            *x == 0
-                || *y == 0
-                || *x == input.dimensions.0 - 1
-                || *y == input.dimensions.1 - 1
-                || (0..*x).all(|xx| input.map.get(&(xx, *y)).unwrap() < height)
-                || (*x + 1..input.dimensions.0).all(|xx| input.map.get(&(xx, *y)).unwrap() < height)
-                || (0..*y).all(|yy| input.map.get(&(*x, yy)).unwrap() < height)
-                || (*y + 1..input.dimensions.1).all(|yy| input.map.get(&(*x, yy)).unwrap() < height)
-        })
-        .count()
+fn part1(input: &Forest) -> usize {
+    input.visible_count()
 }
 
 #[aoc(day8, part2)]
-fn part2(input: &TreeMap) -> i32 {
-    input
-        .map
-        .iter()
-        .map(|((x, y), height)| {
-            let mut left = 0;
-            let mut right = 0;
-            let mut up = 0;
-            let mut down = 0;
-            for xx in (0..*x).rev() {
-                // Reverse because this goes from the tree out
-                left += 1;
-                if input.map.get(&(xx, *y)).unwrap() >= height {
-                    break;
-                }
-            }
-            for xx in *x + 1..input.dimensions.0 {
-                right += 1;
-                if input.map.get(&(xx, *y)).unwrap() >= height {
-                    break;
-                }
-            }
-            for yy in (0..*y).rev() {
-                // Reverse because this goes from the tree out
-                up += 1;
-                if input.map.get(&(*x, yy)).unwrap() >= height {
-                    break;
-                }
-            }
-            for yy in *y + 1..input.dimensions.1 {
-                down += 1;
-                if input.map.get(&(*x, yy)).unwrap() >= height {
-                    break;
-                }
-            }
-            left * right * up * down
-        })
-        .max()
-        .unwrap()
+fn part2(input: &Forest) -> usize {
+    input.best_scenic_score()
+}
+
+pub(crate) fn generator(input: &str) -> Result<Box<dyn Solver>, ParseError> {
+    let solver = DaySolver::new(
+        8,
+        parse(input)?,
+        |i| Ok(part1(i).into()),
+        |i| Ok(part2(i).into()),
+    );
+    Ok(Box::new(solver))
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use crate::testing::Xorshift;
+    use std::collections::HashMap;
 
-    const INPUT: &'static str = "30373
+    const INPUT: &str = "30373
 25512
 65332
 33549
@@ -100,8 +146,89 @@ pub mod tests {
 
     #[test]
     fn solve_day_8() {
-        let input = parse(INPUT);
+        let input = parse(INPUT).unwrap();
         assert_eq!(part1(&input), 21);
         assert_eq!(part2(&input), 8);
     }
+
+    // The first version, rescanning rows and columns from every tree of a HashMap
+    fn reference(forest: &Forest) -> (usize, usize) {
+        let dimensions = (forest.width as i32, forest.height as i32);
+        let map: HashMap<(i32, i32), i32> = (0..forest.height)
+            .flat_map(|y| (0..forest.width).map(move |x| (x, y)))
+            .map(|(x, y)| ((x as i32, y as i32), forest.height_at(x, y) as i32))
+            .collect();
+        let visible = map
+            .iter()
+            .filter(|((x, y), height)| {
+                *x == 0
+                    || *y == 0
+                    || *x == dimensions.0 - 1
+                    || *y == dimensions.1 - 1
+                    || (0..*x).all(|xx| map[&(xx, *y)] < **height)
+                    || (*x + 1..dimensions.0).all(|xx| map[&(xx, *y)] < **height)
+                    || (0..*y).all(|yy| map[&(*x, yy)] < **height)
+                    || (*y + 1..dimensions.1).all(|yy| map[&(*x, yy)] < **height)
+            })
+            .count();
+        let viewing_distance = |trees: &mut dyn Iterator<Item = i32>, height: i32| {
+            let mut distance = 0;
+            for tree in trees {
+                distance += 1;
+                if tree >= height {
+                    break;
+                }
+            }
+            distance
+        };
+        let scenic_score = map
+            .iter()
+            .map(|((x, y), height)| {
+                let left = viewing_distance(&mut (0..*x).rev().map(|xx| map[&(xx, *y)]), *height);
+                let right = viewing_distance(
+                    &mut (*x + 1..dimensions.0).map(|xx| map[&(xx, *y)]),
+                    *height,
+                );
+                let up = viewing_distance(&mut (0..*y).rev().map(|yy| map[&(*x, yy)]), *height);
+                let down = viewing_distance(
+                    &mut (*y + 1..dimensions.1).map(|yy| map[&(*x, yy)]),
+                    *height,
+                );
+                left * right * up * down
+            })
+            .max()
+            .unwrap();
+        (visible, scenic_score)
+    }
+
+    #[test]
+    fn day_8_matches_reference() {
+        let mut random = Xorshift::new(2022);
+        for _ in 0..50 {
+            let width = 1 + random.below(12);
+            let height = 1 + random.below(12);
+            let input = (0..height)
+                .map(|_| {
+                    (0..width)
+                        .map(|_| char::from(b'0' + random.below(10) as u8))
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let forest = parse(&input).unwrap();
+            assert_eq!(
+                (forest.visible_count(), forest.best_scenic_score()),
+                reference(&forest),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn day_8_parse_error() {
+        let err = parse("303\n2551").unwrap_err();
+        assert_eq!(err, ParseError::new(8, 2, 5, "a row of 3 trees"));
+        let err = parse("303\n2x5").unwrap_err();
+        assert_eq!(err, ParseError::new(8, 2, 2, "a tree height digit"));
+    }
 }
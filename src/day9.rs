@@ -1,32 +1,45 @@
 use std::collections::HashSet;
 
+use crate::error::{Line, ParseError};
+use crate::solver::{DaySolver, Solver};
+
 #[derive(Debug)]
-enum Instruction {
+pub enum Instruction {
     Up(i32),
     Down(i32),
     Left(i32),
     Right(i32),
 }
 
-#[aoc_generator(day9)]
-fn parse(input: &str) -> Vec<Instruction> {
-    input
-        .lines()
-        .map(|l| {
-            let mut splitted = l.split(" ");
-            let dir = splitted.next().unwrap();
-            let steps = splitted.next().unwrap().parse::<i32>().unwrap();
+/// The motions of the head, one per line of the puzzle input
+pub fn parse_instructions(input: &str) -> Result<Vec<Instruction>, ParseError> {
+    Line::all(9, input)
+        .map(|line| {
+            let mut splitted = line.text.split(' ');
+            let dir = line.field(splitted.next(), "U, D, L or R")?;
+            let text = line.field(splitted.next(), "a step count")?;
+            // Unsigned, since the head can't go back a negative number of steps
+            let steps = line.parse::<u32>(text, "a step count")?;
+            let steps = i32::try_from(steps).map_err(|_| line.error(text, "a step count"))?;
+            if let Some(extra) = splitted.next() {
+                return Err(line.error(extra, "the end of the line"));
+            }
             match dir {
-                "U" => Instruction::Up(steps),
-                "D" => Instruction::Down(steps),
-                "L" => Instruction::Left(steps),
-                "R" => Instruction::Right(steps),
-                _ => unimplemented!(),
+                "U" => Ok(Instruction::Up(steps)),
+                "D" => Ok(Instruction::Down(steps)),
+                "L" => Ok(Instruction::Left(steps)),
+                "R" => Ok(Instruction::Right(steps)),
+                _ => Err(line.error(dir, "U, D, L or R")),
             }
         })
         .collect()
 }
 
+#[aoc_generator(day9)]
+fn parse(input: &str) -> Result<Vec<Instruction>, ParseError> {
+    parse_instructions(input)
+}
+
 struct Head((i32, i32));
 
 impl Head {
@@ -93,48 +106,195 @@ impl Knot {
     }
 }
 
-#[aoc(day9, part1)]
-fn part1(input: &[Instruction]) -> usize {
-    let mut head = Head((0, 0));
-    let mut tail = Knot((0, 0));
-    let mut visited_positions: HashSet<(i32, i32)> = HashSet::default();
-    visited_positions.insert((0, 0));
-
-    for inst in input {
-        head.apply_instruction(inst, |head_pos| {
-            tail.follow(head_pos);
-            visited_positions.insert(tail.0);
+/// A rope, from its head to its tail
+pub struct Rope {
+    head: Head,
+    knots: Vec<Knot>,
+}
+
+/// Where every knot of a rope was, after each step of the head
+pub struct Trajectory {
+    steps: Vec<Vec<(i32, i32)>>,
+}
+
+impl Rope {
+    /// A rope with that many knots (head included), all starting at (0, 0).
+    /// Panics with 0 knots, since a rope needs at least a head.
+    pub fn new(knots: usize) -> Rope {
+        assert!(knots > 0, "A rope needs at least a head");
+        Rope {
+            head: Head((0, 0)),
+            knots: (1..knots).map(|_| Knot((0, 0))).collect(),
+        }
+    }
+
+    /// Positions of the knots, the head being at index 0
+    pub fn positions(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        std::iter::once(self.head.0).chain(self.knots.iter().map(|knot| knot.0))
+    }
+
+    /// Moves the head one step at a time, calling f with the rope after each step
+    pub fn apply_instruction<F: FnMut(&Rope)>(&mut self, instr: &Instruction, mut f: F) {
+        // Head::apply_instruction gives us the positions one by one, but only moves itself at
+        // the end, so we keep track of the head separately until then
+        let mut head = Head(self.head.0);
+        head.apply_instruction(instr, |head_pos| {
+            self.head.0 = head_pos;
+            let mut leader = head_pos;
+            for knot in self.knots.iter_mut() {
+                knot.follow(leader);
+                leader = knot.0;
+            }
+            f(self);
         });
     }
 
-    visited_positions.len()
+    fn tail(&self) -> (i32, i32) {
+        self.knots.last().map_or(self.head.0, |knot| knot.0)
+    }
+
+    /// Runs every instruction, only remembering the positions the tail went through
+    pub fn tail_visits(&mut self, instructions: &[Instruction]) -> HashSet<(i32, i32)> {
+        let mut visited = HashSet::from([self.tail()]);
+        for instr in instructions {
+            self.apply_instruction(instr, |rope| {
+                visited.insert(rope.tail());
+            });
+        }
+        visited
+    }
+
+    /// Runs every instruction, remembering the whole rope at the start and after each step
+    pub fn trajectory(&mut self, instructions: &[Instruction]) -> Trajectory {
+        let mut steps = vec![self.positions().collect()];
+        for instr in instructions {
+            self.apply_instruction(instr, |rope| steps.push(rope.positions().collect()));
+        }
+        Trajectory { steps }
+    }
 }
 
-#[aoc(day9, part2)]
-fn part2(input: &[Instruction]) -> usize {
-    let mut head = Head((0, 0));
-    let mut rope: Vec<Knot> = (0..9).map(|_| Knot((0, 0))).collect();
-    let mut visited_positions: HashSet<(i32, i32)> = HashSet::default();
-
-    for inst in input {
-        head.apply_instruction(inst, |head_pos| {
-            rope[0].follow(head_pos);
-            for i in 1..9 {
-                let pos = rope[i - 1].0;
-                rope[i].follow(pos);
+impl Trajectory {
+    /// Positions of the whole rope, the first one being before any instruction
+    pub fn steps(&self) -> &[Vec<(i32, i32)>] {
+        &self.steps
+    }
+
+    /// Every position a knot went through (0 being the head), None if the rope doesn't have
+    /// that knot
+    pub fn visited_by(&self, knot: usize) -> Option<HashSet<(i32, i32)>> {
+        self.steps
+            .iter()
+            .map(|positions| positions.get(knot).copied())
+            .collect()
+    }
+
+    /// One line per knot per step, with a header : step,knot,x,y
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("step,knot,x,y\n");
+        for (step, positions) in self.steps.iter().enumerate() {
+            for (knot, (x, y)) in positions.iter().enumerate() {
+                csv.push_str(&format!("{step},{knot},{x},{y}\n"));
             }
-            visited_positions.insert(rope[8].0);
+        }
+        csv
+    }
+}
+
+// Smallest grid holding the start and a set of positions, as (min x, max x, min y, max y)
+type Bounds = (i32, i32, i32, i32);
+
+fn bounds(positions: impl Iterator<Item = (i32, i32)>) -> Bounds {
+    positions.fold((0, 0, 0, 0), |(min_x, max_x, min_y, max_y), (x, y)| {
+        (min_x.min(x), max_x.max(x), min_y.min(y), max_y.max(y))
+    })
+}
+
+// Labels of the knots after the head : past 9 knots, we go on with letters that can't be
+// mistaken for the head, the tail or the start
+const KNOT_LABELS: &str = "123456789ABCDEFGIJKLMNOPQRSUVWXYZabcdefghijklmnopqrtuvwxyz";
+
+// Draws the grid like in the puzzle, with y going up : the first knot on a position is the one
+// we see, then the start, then the visited positions
+fn draw(
+    knots: &[(i32, i32)],
+    visited: Option<&HashSet<(i32, i32)>>,
+    (min_x, max_x, min_y, max_y): Bounds,
+) -> String {
+    let label = |knot: usize| match (knot, knots.len()) {
+        (0, _) => 'H',
+        (1, 2) => 'T',
+        // Even longer ropes all end with *
+        _ => KNOT_LABELS.chars().nth(knot - 1).unwrap_or('*'),
+    };
+    (min_y..=max_y)
+        .rev()
+        .map(|y| {
+            (min_x..=max_x)
+                .map(|x| match knots.iter().position(|knot| *knot == (x, y)) {
+                    Some(knot) => label(knot),
+                    None if (x, y) == (0, 0) => 's',
+                    None if visited.is_some_and(|v| v.contains(&(x, y))) => '#',
+                    None => '.',
+                })
+                .collect::<String>()
         })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl Rope {
+    /// The rope like in the puzzle (H, 1..9 or T, s for the start), on the smallest grid.
+    /// Knots past the 9th are letters, and past the 58th they are all *
+    pub fn render(&self) -> String {
+        let knots: Vec<_> = self.positions().collect();
+        draw(&knots, None, bounds(knots.iter().copied()))
+    }
+}
+
+impl Trajectory {
+    fn bounds(&self) -> Bounds {
+        bounds(self.steps.iter().flatten().copied())
+    }
+
+    /// The rope after a step, on a grid big enough for all the steps so that they line up.
+    /// None if there is no such step.
+    pub fn render_step(&self, step: usize) -> Option<String> {
+        Some(draw(self.steps.get(step)?, None, self.bounds()))
+    }
+
+    /// Positions visited by a knot, like the summary at the end of the puzzle examples.
+    /// None if the rope doesn't have that knot.
+    pub fn render_visited(&self, knot: usize) -> Option<String> {
+        Some(draw(&[], Some(&self.visited_by(knot)?), self.bounds()))
     }
+}
+
+#[aoc(day9, part1)]
+fn part1(input: &[Instruction]) -> usize {
+    Rope::new(2).tail_visits(input).len()
+}
+
+#[aoc(day9, part2)]
+fn part2(input: &[Instruction]) -> usize {
+    Rope::new(10).tail_visits(input).len()
+}
 
-    visited_positions.len()
+pub(crate) fn generator(input: &str) -> Result<Box<dyn Solver>, ParseError> {
+    let solver = DaySolver::new(
+        9,
+        parse(input)?,
+        |i| Ok(part1(i).into()),
+        |i| Ok(part2(i).into()),
+    );
+    Ok(Box::new(solver))
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
 
-    const INPUT: &'static str = "R 4
+    const INPUT: &str = "R 4
 U 4
 L 3
 D 1
@@ -143,7 +303,7 @@ D 1
 L 5
 R 2";
 
-    const INPUT_TWO: &'static str = "R 5
+    const INPUT_TWO: &str = "R 5
 U 8
 L 8
 D 3
@@ -154,14 +314,150 @@ U 20";
 
     #[test]
     fn solve_day_9() {
-        let input = parse(INPUT);
+        let input = parse(INPUT).unwrap();
         assert_eq!(part1(&input), 13);
         assert_eq!(part2(&input), 1);
 
-        let input = parse(INPUT_TWO);
+        let input = parse(INPUT_TWO).unwrap();
         assert_eq!(part2(&input), 36);
     }
 
+    #[test]
+    fn day_9_rope() {
+        let input = parse(INPUT).unwrap();
+        let trajectory = Rope::new(3).trajectory(&input);
+        // One position before the instructions, then one per step of the head
+        assert_eq!(trajectory.steps().len(), 1 + 4 + 4 + 3 + 1 + 4 + 1 + 5 + 2);
+        assert_eq!(trajectory.steps()[4], vec![(4, 0), (3, 0), (2, 0)]);
+        assert_eq!(trajectory.visited_by(0).unwrap().len(), 21);
+        assert_eq!(trajectory.visited_by(1).unwrap().len(), 13);
+        assert_eq!(
+            Rope::new(3).tail_visits(&input),
+            trajectory.visited_by(2).unwrap()
+        );
+        assert_eq!(
+            Rope::new(1).tail_visits(&input),
+            trajectory.visited_by(0).unwrap()
+        );
+        assert_eq!(trajectory.visited_by(3), None);
+
+        let csv = trajectory.to_csv();
+        assert!(csv.starts_with("step,knot,x,y\n0,0,0,0\n0,1,0,0\n0,2,0,0\n1,0,1,0\n"));
+        assert!(csv.ends_with("24,0,2,2\n24,1,1,2\n24,2,2,2\n"));
+    }
+
+    // Frames from the puzzle text, without the "(H covers T, s)" notes
+    fn frames(text: &str) -> Vec<&str> {
+        text.split("\n\n").collect()
+    }
+
+    #[test]
+    fn day_9_render() {
+        let input = parse(INPUT).unwrap();
+        let mut rope = Rope::new(2);
+        let trajectory = rope.trajectory(&input);
+        let expected = frames(
+            "......
+......
+......
+......
+H.....
+
+......
+......
+......
+......
+TH....
+
+......
+......
+......
+......
+sTH...
+
+......
+......
+......
+......
+s.TH..
+
+......
+......
+......
+......
+s..TH.",
+        );
+        // The steps of R 4, on the grid of the whole example
+        let rendered: Vec<_> = (0..5)
+            .map(|step| trajectory.render_step(step).unwrap())
+            .collect();
+        assert_eq!(rendered, expected);
+
+        assert_eq!(
+            trajectory.render_visited(1).unwrap(),
+            "..##..
+...##.
+.####.
+....#.
+s###.."
+        );
+        assert_eq!(rope.render(), ".TH\n...\ns..");
+        assert_eq!(trajectory.render_step(trajectory.steps().len()), None);
+        assert_eq!(trajectory.render_visited(2), None);
+
+        let trajectory = Rope::new(10).trajectory(&input);
+        assert_eq!(
+            trajectory
+                .render_step(trajectory.steps().len() - 1)
+                .unwrap(),
+            "......
+......
+.1H3..
+.5....
+6....."
+        );
+
+        let input = parse(INPUT_TWO).unwrap();
+        let trajectory = Rope::new(10).trajectory(&input);
+        assert_eq!(
+            trajectory.render_visited(9).unwrap(),
+            "..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+#.........................
+#.............###.........
+#............#...#........
+.#..........#.....#.......
+..#..........#.....#......
+...#........#.......#.....
+....#......s.........#....
+.....#..............#.....
+......#............#......
+.......#..........#.......
+........#........#........
+.........########........."
+        );
+    }
+
+    #[test]
+    fn day_9_long_rope() {
+        let mut rope = Rope::new(60);
+        rope.apply_instruction(&Instruction::Up(1), |_| ());
+        rope.apply_instruction(&Instruction::Right(60), |_| ());
+        let rendered = rope.render();
+        let (knots, start) = rendered.split_once('\n').unwrap();
+        // The tail ends up at x = 1, the head at x = 60
+        let labels: String = KNOT_LABELS.chars().rev().collect();
+        assert_eq!(knots, format!(".*{labels}H"));
+        assert_eq!(start, format!("s{}", ".".repeat(60)));
+    }
+
     #[test]
     fn day_9_follow() {
         // .....    .....    .....
@@ -232,4 +528,16 @@ U 20";
         tail.follow(head);
         assert_eq!(tail.0, (2, 2));
     }
+
+    #[test]
+    fn day_9_parse_error() {
+        let err = parse("R 4\nX 2").unwrap_err();
+        assert_eq!(err, ParseError::new(9, 2, 1, "U, D, L or R"));
+        let err = parse("R -5").unwrap_err();
+        assert_eq!(err, ParseError::new(9, 1, 3, "a step count"));
+        let err = parse("R").unwrap_err();
+        assert_eq!(err, ParseError::new(9, 1, 2, "a step count"));
+        let err = parse("R 4 junk").unwrap_err();
+        assert_eq!(err, ParseError::new(9, 1, 5, "the end of the line"));
+    }
 }
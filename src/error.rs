@@ -0,0 +1,133 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Error returned by the generators when the puzzle input doesn't look like what we expect.
+/// Lines and columns are 1-based, like in an editor.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub day: u32,
+    pub line: usize,
+    pub column: usize,
+    pub expected: String,
+}
+
+impl ParseError {
+    pub fn new(day: u32, line: usize, column: usize, expected: impl Into<String>) -> Self {
+        ParseError {
+            day,
+            line,
+            column,
+            expected: expected.into(),
+        }
+    }
+
+    /// Converts the errors of the aoc_parse generators, which only know about a byte offset
+    pub fn from_aoc_parse(day: u32, err: aoc_parse::ParseError) -> Self {
+        let before = &err.source[..err.location];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line = before.matches('\n').count() + 1;
+        let column = before[line_start..].chars().count() + 1;
+        // aoc_parse has no accessor for the reason, so we strip the location from the message
+        let message = err.to_string();
+        let expected = match message.rsplit_once(" at ") {
+            Some((reason, _location)) => reason.to_string(),
+            None => message,
+        };
+        ParseError::new(day, line, column, expected)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "day {}, line {}, column {}: {}",
+            self.day, self.line, self.column, self.expected
+        )
+    }
+}
+
+// aoc_runner prints the Debug form of generator errors, so we make it the readable one
+impl fmt::Debug for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ParseError({self})")
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A line of puzzle input that remembers where it comes from, so that errors can point at it
+#[derive(Debug, Clone, Copy)]
+pub struct Line<'a> {
+    pub day: u32,
+    /// 1-based line number
+    pub number: usize,
+    pub text: &'a str,
+}
+
+impl<'a> Line<'a> {
+    pub fn all(day: u32, input: &'a str) -> impl Iterator<Item = Line<'a>> {
+        input.lines().enumerate().map(move |(idx, text)| Line {
+            day,
+            number: idx + 1,
+            text,
+        })
+    }
+
+    /// Error pointing at `part`, which should be a slice of this line's text.
+    /// Anything else is reported at the end of the line.
+    pub fn error(&self, part: &str, expected: impl Into<String>) -> ParseError {
+        let start = self.text.as_ptr() as usize;
+        let offset = (part.as_ptr() as usize)
+            .checked_sub(start)
+            .filter(|offset| *offset <= self.text.len())
+            .unwrap_or(self.text.len());
+        let column = self.text[..offset].chars().count() + 1;
+        ParseError::new(self.day, self.number, column, expected)
+    }
+
+    pub fn error_at_end(&self, expected: impl Into<String>) -> ParseError {
+        self.error(&self.text[self.text.len()..], expected)
+    }
+
+    /// Unwraps a field obtained from splitting this line, reporting the end of the line if
+    /// the input was truncated
+    pub fn field(&self, part: Option<&'a str>, expected: &str) -> Result<&'a str, ParseError> {
+        part.ok_or_else(|| self.error_at_end(expected))
+    }
+
+    pub fn parse<T: FromStr>(&self, part: &str, expected: &str) -> Result<T, ParseError> {
+        part.parse().map_err(|_| self.error(part, expected))
+    }
+
+    /// Skips `prefix` at the start of the line
+    pub fn strip_prefix(&self, prefix: &str) -> Result<&'a str, ParseError> {
+        self.text
+            .strip_prefix(prefix)
+            .ok_or_else(|| self.error(self.text, format!("{prefix:?}")))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_error_location() {
+        let lines: Vec<Line> = Line::all(42, "1 2\n3 x").collect();
+        let line = lines[1];
+        let mut split = line.text.split(' ');
+        let _three = split.next();
+        let x = split.next().unwrap();
+        assert_eq!(
+            line.parse::<i32>(x, "a number"),
+            Err(ParseError::new(42, 2, 3, "a number"))
+        );
+        assert_eq!(
+            line.field(split.next(), "a number")
+                .unwrap_err()
+                .to_string(),
+            "day 42, line 2, column 4: a number"
+        );
+    }
+}
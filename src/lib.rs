@@ -8,6 +8,7 @@ pub mod day1;
 pub mod day10;
 pub mod day11;
 pub mod day12;
+pub mod day13;
 pub mod day14;
 pub mod day15;
 pub mod day2;
@@ -18,5 +19,11 @@ pub mod day6;
 pub mod day7;
 pub mod day8;
 pub mod day9;
+pub mod error;
+pub mod solver;
+#[cfg(test)]
+mod testing;
+
+pub use solver::{generate, puzzles, solve, Answer, SolveError, Solver};
 
 aoc_runner_derive::aoc_lib! { year = 2022 }
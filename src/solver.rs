@@ -0,0 +1,225 @@
+use std::fmt;
+
+use crate::day11::WorryError;
+use crate::error::ParseError;
+use crate::{
+    day1, day10, day11, day12, day13, day14, day15, day2, day3, day4, day5, day6, day7, day8, day9,
+};
+
+/// Days that have a solution, in order
+pub const DAYS: [u32; 15] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+
+/// Every puzzle has two parts
+pub const PARTS: [u32; 2] = [1, 2];
+
+/// What a part returns : most of them are numbers, some are text (crates, CRT image...)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+    Number(i64),
+    Text(String),
+}
+
+macro_rules! answer_from_number {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for Answer {
+                fn from(value: $t) -> Answer {
+                    Answer::Number(value as i64)
+                }
+            }
+        )*
+    };
+}
+
+answer_from_number!(i32, u32, i64, usize, isize);
+
+impl From<String> for Answer {
+    fn from(value: String) -> Answer {
+        Answer::Text(value)
+    }
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Answer::Number(n) => write!(f, "{n}"),
+            Answer::Text(t) => write!(f, "{t}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolveError {
+    UnknownDay(u32),
+    UnknownPart { day: u32, part: u32 },
+    Parse(ParseError),
+    NoAnswer { day: u32, part: u32 },
+    Worry(WorryError),
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::UnknownDay(day) => write!(f, "no solution for day {day}"),
+            SolveError::UnknownPart { day, part } => {
+                write!(f, "no solution for day {day} part {part}")
+            }
+            SolveError::Parse(err) => write!(f, "{err}"),
+            SolveError::NoAnswer { day, part } => {
+                write!(f, "no answer for day {day} part {part} in this input")
+            }
+            SolveError::Worry(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+impl From<ParseError> for SolveError {
+    fn from(err: ParseError) -> SolveError {
+        SolveError::Parse(err)
+    }
+}
+
+impl From<WorryError> for SolveError {
+    fn from(err: WorryError) -> SolveError {
+        SolveError::Worry(err)
+    }
+}
+
+/// The output of a day's generator, ready to be solved
+pub trait Solver {
+    fn solve(&self, part: u32) -> Result<Answer, SolveError>;
+}
+
+type Part<G> = fn(&G) -> Result<Answer, SolveError>;
+
+/// Glues a generator output to the two parts that take it
+pub(crate) struct DaySolver<G> {
+    day: u32,
+    input: G,
+    part1: Part<G>,
+    part2: Part<G>,
+}
+
+impl<G> DaySolver<G> {
+    pub(crate) fn new(day: u32, input: G, part1: Part<G>, part2: Part<G>) -> Self {
+        DaySolver {
+            day,
+            input,
+            part1,
+            part2,
+        }
+    }
+}
+
+impl<G> Solver for DaySolver<G> {
+    fn solve(&self, part: u32) -> Result<Answer, SolveError> {
+        match part {
+            1 => (self.part1)(&self.input),
+            2 => (self.part2)(&self.input),
+            _ => Err(SolveError::UnknownPart {
+                day: self.day,
+                part,
+            }),
+        }
+    }
+}
+
+/// For the parts that return None when the input has no answer
+pub(crate) fn found<T: Into<Answer>>(
+    day: u32,
+    part: u32,
+    answer: Option<T>,
+) -> Result<Answer, SolveError> {
+    answer
+        .map(Into::into)
+        .ok_or(SolveError::NoAnswer { day, part })
+}
+
+type Generator = fn(&str) -> Result<Box<dyn Solver>, ParseError>;
+
+fn generator(day: u32) -> Option<Generator> {
+    let generator: Generator = match day {
+        1 => day1::generator,
+        2 => day2::generator,
+        3 => day3::generator,
+        4 => day4::generator,
+        5 => day5::generator,
+        6 => day6::generator,
+        7 => day7::generator,
+        8 => day8::generator,
+        9 => day9::generator,
+        10 => day10::generator,
+        11 => day11::generator,
+        12 => day12::generator,
+        13 => day13::generator,
+        14 => day14::generator,
+        15 => day15::generator,
+        _ => return None,
+    };
+    Some(generator)
+}
+
+/// Every (day, part) that can be solved
+pub fn puzzles() -> impl Iterator<Item = (u32, u32)> {
+    DAYS.into_iter()
+        .flat_map(|day| PARTS.into_iter().map(move |part| (day, part)))
+}
+
+/// Runs the generator of a day, so that both parts can be solved without parsing twice
+pub fn generate(day: u32, input: &str) -> Result<Box<dyn Solver>, SolveError> {
+    let generator = generator(day).ok_or(SolveError::UnknownDay(day))?;
+    Ok(generator(input)?)
+}
+
+pub fn solve(day: u32, part: u32, input: &str) -> Result<Answer, SolveError> {
+    generate(day, input)?.solve(part)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn solve_through_registry() {
+        assert_eq!(solve(1, 1, "1000\n\n2000"), Ok(Answer::Number(2000)));
+        assert_eq!(
+            solve(6, 2, "mjqjpqmgbljsphdztnvjfqwrcgsmlb"),
+            Ok(Answer::Number(19))
+        );
+        assert_eq!(solve(16, 1, ""), Err(SolveError::UnknownDay(16)));
+        assert_eq!(
+            solve(1, 3, "1"),
+            Err(SolveError::UnknownPart { day: 1, part: 3 })
+        );
+        assert!(matches!(solve(1, 1, "x"), Err(SolveError::Parse(_))));
+        assert_eq!(
+            solve(6, 1, "ab"),
+            Err(SolveError::NoAnswer { day: 6, part: 1 })
+        );
+        let too_many = "[A]\n 1   2 \n\nmove 5 from 1 to 2";
+        assert_eq!(
+            solve(5, 2, too_many),
+            Err(SolveError::NoAnswer { day: 5, part: 2 })
+        );
+        assert_eq!(
+            solve(12, 1, "SzE"),
+            Err(SolveError::NoAnswer { day: 12, part: 1 })
+        );
+        let overflow = "Monkey 0:
+  Starting items: 3037000500
+  Operation: new = old * old
+  Test: divisible by 2
+    If true: throw to monkey 0
+    If false: throw to monkey 0";
+        let err = WorryError::Overflow {
+            round: 1,
+            monkey: 0,
+            item: 0,
+            worry_level: 3037000500,
+        };
+        assert_eq!(solve(11, 1, overflow), Err(SolveError::Worry(err)));
+        assert_eq!(puzzles().count(), 30);
+    }
+}
@@ -0,0 +1,18 @@
+//! Helpers shared by the tests of several days
+
+/// Small xorshift, so that random test inputs are the same on every run
+pub(crate) struct Xorshift(u32);
+
+impl Xorshift {
+    pub(crate) fn new(seed: u32) -> Xorshift {
+        Xorshift(seed)
+    }
+
+    /// Next number, between 0 and max excluded
+    pub(crate) fn below(&mut self, max: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 as usize % max
+    }
+}
@@ -0,0 +1,69 @@
+// Checks every registered day against real puzzle inputs and the answers AoC accepted.
+//
+// Fixtures live in tests/fixtures/dayN/ (or in $AOC22_FIXTURES/dayN/) :
+// - input.txt : the puzzle input
+// - answers.toml : `part1 = 1234` and `part2 = 5678`, or strings for text answers
+//
+// Everyone has different inputs, and we're not supposed to share them, so fixtures aren't
+// committed : days (or parts) without a fixture are skipped.
+
+use std::path::PathBuf;
+
+use aoc22::{puzzles, Answer};
+
+fn fixtures_dir() -> PathBuf {
+    match std::env::var_os("AOC22_FIXTURES") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures"),
+    }
+}
+
+fn expected_answer(value: &toml::Value) -> Answer {
+    match value {
+        toml::Value::Integer(n) => Answer::Number(*n),
+        toml::Value::String(s) => Answer::Text(s.trim_end().to_string()),
+        other => panic!("Answers should be integers or strings, got {other}"),
+    }
+}
+
+#[test]
+fn golden_answers() {
+    let dir = fixtures_dir();
+    let mut checked = 0;
+    let mut failures = Vec::new();
+
+    for (day, part) in puzzles() {
+        let day_dir = dir.join(format!("day{day}"));
+        let (Ok(input), Ok(answers)) = (
+            std::fs::read_to_string(day_dir.join("input.txt")),
+            std::fs::read_to_string(day_dir.join("answers.toml")),
+        ) else {
+            continue;
+        };
+        let answers: toml::Table = answers
+            .parse()
+            .unwrap_or_else(|err| panic!("Invalid answers.toml for day {day}: {err}"));
+        let Some(expected) = answers.get(&format!("part{part}")) else {
+            continue;
+        };
+        let expected = expected_answer(expected);
+
+        let actual = match aoc22::solve(day, part, input.trim_end_matches('\n')) {
+            Ok(Answer::Text(text)) => Answer::Text(text.trim_end().to_string()),
+            Ok(answer) => answer,
+            Err(err) => {
+                failures.push(format!("day {day} part {part}: {err}"));
+                continue;
+            }
+        };
+        checked += 1;
+        if actual != expected {
+            failures.push(format!(
+                "day {day} part {part}: expected {expected}, got {actual}"
+            ));
+        }
+    }
+
+    eprintln!("Checked {checked} golden answers in {}", dir.display());
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}